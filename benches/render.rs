@@ -0,0 +1,103 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use image::{Rgb, RgbImage};
+use nalgebra_glm as glm;
+use raydium::{
+    atlas::{AtlasRegion, TextureAtlas},
+    camera::Camera,
+    gfx::{ColumnBuffer, PixelBuffer},
+    map::{Tile, TileMap},
+    raycast::{Raycaster, WORLD_MAP},
+};
+
+const W: u32 = 1920;
+const H: u32 = 1080;
+
+/// Four 16x16 gradient textures side by side, one per wall tile.
+fn atlas() -> TextureAtlas {
+    let img = RgbImage::from_fn(64, 16, |x, y| {
+        Rgb([(x * 4) as u8, (y * 16) as u8, ((x ^ y) * 16) as u8])
+    });
+    let mut atlas = TextureAtlas::from_image(&image::DynamicImage::ImageRgb8(img));
+    for id in 1..=4 {
+        atlas
+            .set_region(Tile(id), AtlasRegion::grid(id as u32 - 1, 0, 16, 16))
+            .unwrap();
+    }
+    atlas
+}
+
+fn scene() -> (TileMap, Camera) {
+    let mut map = TileMap::from_rows(&WORLD_MAP).unwrap();
+    map.fill_floor(Tile(3));
+    map.fill_ceiling(Tile(2));
+    let cam = Camera::new(glm::vec2(22., 12.), glm::vec2(-1., 0.));
+    (map, cam)
+}
+
+fn render_1080p(c: &mut Criterion) {
+    let (map, cam) = scene();
+    let mut raycaster = Raycaster::with_atlas(atlas());
+
+    let mut group = c.benchmark_group("render_1080p");
+    group.throughput(Throughput::Elements((W * H) as u64));
+
+    let mut image = RgbImage::new(W, H);
+    group.bench_function("serial", |b| {
+        b.iter(|| {
+            image.clear_black_horizon(cam.horizon(H));
+            raycaster.render(&mut image, &map, &cam).unwrap();
+        })
+    });
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut frame = ColumnBuffer::new(W, H);
+    let mut counts = vec![1, 2, 4, cores];
+    counts.sort_unstable();
+    counts.dedup();
+    for threads in counts {
+        group.bench_with_input(BenchmarkId::new("threaded", threads), &threads, |b, &n| {
+            b.iter(|| {
+                frame.clear_black_horizon(cam.horizon(H));
+                raycaster
+                    .render_threaded(&mut frame, &map, &cam, n)
+                    .unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The same single-threaded frame drawn into rows directly, or into columns
+/// and transposed into rows afterwards like `SDLTextureBuf` does on flush.
+fn layout_1080p(c: &mut Criterion) {
+    let (map, cam) = scene();
+    let mut raycaster = Raycaster::with_atlas(atlas());
+
+    let mut group = c.benchmark_group("layout_1080p");
+    group.throughput(Throughput::Elements((W * H) as u64));
+
+    let mut rows = RgbImage::new(W, H);
+    group.bench_function("row_major", |b| {
+        b.iter(|| {
+            rows.clear_black_horizon(cam.horizon(H));
+            raycaster.render(&mut rows, &map, &cam).unwrap();
+        })
+    });
+
+    let mut columns = ColumnBuffer::new(W, H);
+    group.bench_function("column_major", |b| {
+        b.iter(|| {
+            columns.clear_black_horizon(cam.horizon(H));
+            raycaster.render(&mut columns, &map, &cam).unwrap();
+            columns.transpose_into(&mut rows);
+        })
+    });
+
+    group.bench_function("transpose", |b| {
+        b.iter(|| columns.transpose_into(&mut rows))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, render_1080p, layout_1080p);
+criterion_main!(benches);
@@ -0,0 +1,262 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use sdl2::{event::Event, EventPump, Sdl};
+
+use crate::input::{InputMap, InputState};
+
+/// Something that can show a finished frame, the last step of every frame
+/// run by [`App`].
+pub trait Present {
+    fn present(&mut self) -> anyhow::Result<()>;
+
+    /// Called for every SDL event before the game sees it, to follow the
+    /// window being resized and the like. Does nothing by default.
+    fn event(&mut self, _event: &Event) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Whether the game loop should keep going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Quit,
+}
+
+/// Game driven by [`App::run`].
+pub trait Game {
+    type Renderer: Present;
+
+    /// Called for every SDL event before the frame's updates. The default
+    /// quits when the window is closed.
+    fn event(&mut self, event: &Event) -> Control {
+        match event {
+            Event::Quit { .. } => Control::Quit,
+            _ => Control::Continue,
+        }
+    }
+
+    /// Advances the game by one fixed step of `dt` seconds.
+    fn update(&mut self, dt: f32, input: &InputState) -> Control;
+
+    /// Draws the current state. `alpha` in [0, 1) is how far the frame is
+    /// between the last update and the next one, for interpolating motion.
+    fn render(&mut self, renderer: &mut Self::Renderer, alpha: f32) -> anyhow::Result<()>;
+}
+
+/// Accumulates frame time and hands it out in fixed steps.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    /// Longest frame time counted, so one slow frame doesn't snowball into
+    /// more and more updates.
+    max_frame: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    /// Panics if `step` is zero, which no amount of time could be used up
+    /// by.
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "fixed timestep of zero");
+        Self {
+            step,
+            max_frame: Duration::from_millis(250),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// Fixed step running `hz` updates per second. Panics if `hz` is zero.
+    pub fn from_hz(hz: u32) -> Self {
+        assert!(hz > 0, "fixed timestep of 0 Hz");
+        Self::new(Duration::from_secs_f64(1. / hz as f64))
+    }
+
+    pub fn with_max_frame(mut self, max_frame: Duration) -> Self {
+        self.max_frame = max_frame;
+        self
+    }
+
+    #[inline]
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Adds a frame's worth of time, returns how many steps to update by.
+    pub fn advance(&mut self, frame_time: Duration) -> u32 {
+        self.accumulator += frame_time.min(self.max_frame);
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Fraction of a step left over after the last [`FixedTimestep::advance`].
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+/// Frame times over the last [`FrameStats::WINDOW`] frames.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    frames: VecDeque<Duration>,
+    frame_count: u64,
+    updates: u32,
+}
+
+impl FrameStats {
+    pub const WINDOW: usize = 120;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, frame_time: Duration, updates: u32) {
+        if self.frames.len() == Self::WINDOW {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame_time);
+        self.frame_count += 1;
+        self.updates = updates;
+    }
+
+    /// Total frames recorded.
+    #[inline]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Fixed updates run during the last frame.
+    #[inline]
+    pub fn updates_last_frame(&self) -> u32 {
+        self.updates
+    }
+
+    pub fn last(&self) -> Duration {
+        self.frames.back().copied().unwrap_or_default()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.frames.is_empty() {
+            return Duration::ZERO;
+        }
+        self.frames.iter().sum::<Duration>() / self.frames.len() as u32
+    }
+
+    pub fn min(&self) -> Duration {
+        self.frames.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.frames.iter().max().copied().unwrap_or_default()
+    }
+
+    /// Frames per second from the average frame time.
+    pub fn fps(&self) -> f32 {
+        let avg = self.average().as_secs_f32();
+        if avg > 0. {
+            1. / avg
+        } else {
+            0.
+        }
+    }
+}
+
+/// Owns SDL's event pump and input state and runs a [`Game`] with fixed
+/// timestep updates and once per frame rendering.
+pub struct App {
+    sdl: Sdl,
+    event_pump: EventPump,
+    pub input_map: InputMap,
+    input: InputState,
+    /// Mouse movement not yet handed to an update.
+    mouse_pending: (i32, i32),
+    timestep: FixedTimestep,
+    stats: FrameStats,
+}
+
+impl App {
+    /// App updating 60 times per second. Only one can exist at a time since
+    /// it takes SDL's event pump.
+    pub fn new(sdl: &Sdl) -> anyhow::Result<Self> {
+        let event_pump = sdl.event_pump().map_err(|e| anyhow!(e))?;
+        Ok(Self {
+            sdl: sdl.clone(),
+            event_pump,
+            input_map: InputMap::default(),
+            input: InputState::new(),
+            mouse_pending: (0, 0),
+            timestep: FixedTimestep::from_hz(60),
+            stats: FrameStats::new(),
+        })
+    }
+
+    pub fn set_timestep(&mut self, timestep: FixedTimestep) {
+        self.timestep = timestep;
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Hides the cursor and reports mouse movement relative to the last
+    /// frame, for mouse look.
+    pub fn set_relative_mouse_mode(&mut self, on: bool) {
+        self.sdl.mouse().set_relative_mouse_mode(on);
+    }
+
+    /// Runs `game` until it or an event asks to quit.
+    ///
+    /// Input is sampled once per frame. Mouse movement is only reported to
+    /// the first update of a frame so it isn't applied more than once, and
+    /// carried over to the next frame if no update ran.
+    pub fn run<G: Game>(&mut self, game: &mut G, renderer: &mut G::Renderer) -> anyhow::Result<()> {
+        let dt = self.timestep.step().as_secs_f32();
+        let mut last = Instant::now();
+
+        loop {
+            let now = Instant::now();
+            let frame_time = now - last;
+            last = now;
+
+            for event in self.event_pump.poll_iter() {
+                renderer.event(&event)?;
+                if game.event(&event) == Control::Quit {
+                    return Ok(());
+                }
+            }
+
+            self.input
+                .sample(&self.input_map, &self.event_pump.keyboard_state());
+            let mouse = self.event_pump.relative_mouse_state();
+            self.mouse_pending.0 += mouse.x();
+            self.mouse_pending.1 += mouse.y();
+
+            let steps = self.timestep.advance(frame_time);
+            for i in 0..steps {
+                let (dx, dy) = if i == 0 {
+                    std::mem::take(&mut self.mouse_pending)
+                } else {
+                    (0, 0)
+                };
+                self.input.sample_mouse_with(dx, dy);
+                if game.update(dt, &self.input) == Control::Quit {
+                    return Ok(());
+                }
+            }
+
+            game.render(renderer, self.timestep.alpha())?;
+            renderer.present()?;
+
+            self.stats.record(frame_time, steps);
+        }
+    }
+}
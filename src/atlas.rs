@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+
+use crate::map::Tile;
+
+/// Pixel rectangle inside of a [`TextureAtlas`] image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl AtlasRegion {
+    pub const fn new(x: u32, y: u32, w: u32, h: u32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// Region of cell (col, row) in an atlas laid out as a grid of
+    /// `cell_w` x `cell_h` textures.
+    pub const fn grid(col: u32, row: u32, cell_w: u32, cell_h: u32) -> Self {
+        Self::new(col * cell_w, row * cell_h, cell_w, cell_h)
+    }
+}
+
+/// Single image holding many textures, with a table mapping tile ids to the
+/// region of the image used to draw them.
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    image: image::RgbaImage,
+    regions: HashMap<Tile, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(&img))
+    }
+
+    pub fn from_image(img: &image::DynamicImage) -> Self {
+        Self {
+            image: img.to_rgba8(),
+            regions: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    /// Maps `tile` to `region`, replacing any region it had before. Fails if
+    /// the region is empty or does not fit inside of the atlas image.
+    pub fn set_region(&mut self, tile: Tile, region: AtlasRegion) -> anyhow::Result<()> {
+        if region.w == 0 || region.h == 0 {
+            bail!("Atlas region for tile {} is empty", tile.id());
+        }
+        let (w, h) = self.image.dimensions();
+        // checked, as regions reaching past u32::MAX would wrap around
+        if region.x.checked_add(region.w).is_none_or(|r| r > w)
+            || region.y.checked_add(region.h).is_none_or(|r| r > h)
+        {
+            bail!(
+                "Atlas region {:?} for tile {} is outside of the {}x{} atlas",
+                region,
+                tile.id(),
+                w,
+                h
+            );
+        }
+        self.regions.insert(tile, region);
+        Ok(())
+    }
+
+    pub fn with_region(mut self, tile: Tile, region: AtlasRegion) -> anyhow::Result<Self> {
+        self.set_region(tile, region)?;
+        Ok(self)
+    }
+
+    pub fn remove_region(&mut self, tile: Tile) -> Option<AtlasRegion> {
+        self.regions.remove(&tile)
+    }
+
+    #[inline]
+    pub fn region(&self, tile: Tile) -> Option<AtlasRegion> {
+        self.regions.get(&tile).copied()
+    }
+
+    /// Texel (tx, ty) of `region`, coordinates are relative to the region and
+    /// clamped to its edges.
+    #[inline]
+    pub fn texel(&self, region: AtlasRegion, tx: u32, ty: u32) -> image::Rgba<u8> {
+        let x = region.x + tx.min(region.w - 1);
+        let y = region.y + ty.min(region.h - 1);
+        *self.image.get_pixel(x, y)
+    }
+
+    /// Samples `region` at normalized (u, v), nearest neighbour.
+    pub fn sample(&self, region: AtlasRegion, u: f32, v: f32) -> image::Rgba<u8> {
+        let tx = (u * region.w as f32).max(0.) as u32;
+        let ty = (v * region.h as f32).max(0.) as u32;
+        self.texel(region, tx, ty)
+    }
+
+    pub fn image(&self) -> &image::RgbaImage {
+        &self.image
+    }
+}
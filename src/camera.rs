@@ -0,0 +1,338 @@
+use nalgebra_glm as glm;
+
+use crate::{map::TileMap, math};
+
+/// Length of the camera plane for a unit facing direction that gives the
+/// classic ~66 degree horizontal fov.
+pub const DEFAULT_PLANE_LEN: f32 = 0.66;
+
+pub const DEFAULT_RADIUS: f32 = 0.2;
+
+/// Largest collision radius. A camera at least as wide as a cell couldn't
+/// fit between two walls and would be pushed out of both at once.
+pub const MAX_RADIUS: f32 = 0.45;
+
+/// Furthest the horizon can be moved from the middle of the screen, in
+/// screen heights.
+pub const MAX_PITCH: f32 = 0.5;
+
+/// Keeps the camera from touching walls exactly, so flooring its position
+/// never lands inside of a solid cell.
+const SKIN: f32 = 1e-3;
+
+/// First person view into a [`TileMap`].
+///
+/// `dir` is always unit length and `plane` is perpendicular to it, pointing
+/// to the right side of the screen. The map's y axis grows down, so positive
+/// rotations turn the view right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    pos: glm::Vec2,
+    dir: glm::Vec2,
+    plane: glm::Vec2,
+    /// Horizontal fov in degrees.
+    fov: f32,
+    /// Vertical look, how far the horizon is moved down from the middle of
+    /// the screen in screen heights. Positive looks up.
+    pitch: f32,
+    /// Half the side of the square the camera occupies when colliding with
+    /// walls, at most [`MAX_RADIUS`].
+    radius: f32,
+}
+
+impl Camera {
+    /// Camera at `pos` looking along `dir`. A zero or non-finite `dir`
+    /// looks north, towards row 0.
+    pub fn new(pos: glm::Vec2, dir: glm::Vec2) -> Self {
+        let dir = unit(dir).unwrap_or(glm::vec2(0., -1.));
+        Self {
+            pos,
+            dir,
+            plane: perp(dir) * DEFAULT_PLANE_LEN,
+            fov: math::degrees(2. * DEFAULT_PLANE_LEN.atan()),
+            pitch: 0.,
+            radius: DEFAULT_RADIUS,
+        }
+    }
+
+    pub fn with_fov(mut self, degrees: f32) -> Self {
+        self.set_fov(degrees);
+        self
+    }
+
+    /// Sets the collision radius, clamped to [0, [`MAX_RADIUS`]].
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius.clamp(0., MAX_RADIUS);
+        self
+    }
+
+    #[inline]
+    pub const fn pos(&self) -> glm::Vec2 {
+        self.pos
+    }
+
+    #[inline]
+    pub const fn dir(&self) -> glm::Vec2 {
+        self.dir
+    }
+
+    #[inline]
+    pub const fn plane(&self) -> glm::Vec2 {
+        self.plane
+    }
+
+    /// Horizontal fov in degrees.
+    #[inline]
+    pub const fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    #[inline]
+    pub const fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Half the side of the square the camera occupies when colliding with
+    /// walls.
+    #[inline]
+    pub const fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Screen row of the horizon for a screen `height` pixels tall.
+    #[inline]
+    pub fn horizon(&self, height: u32) -> i32 {
+        height as i32 / 2 + (self.pitch * height as f32) as i32
+    }
+
+    /// Places the camera at `pos` without checking for collisions.
+    pub fn set_pos(&mut self, pos: glm::Vec2) {
+        self.pos = pos;
+    }
+
+    /// Faces the camera along `dir`, keeping the current fov. A zero or
+    /// non-finite `dir` is ignored.
+    pub fn set_dir(&mut self, dir: glm::Vec2) {
+        let Some(dir) = unit(dir) else {
+            return;
+        };
+        self.dir = dir;
+        self.update_plane();
+    }
+
+    /// Sets the horizontal fov in degrees, clamped to (1, 179).
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.fov = degrees.clamp(1., 179.);
+        self.update_plane();
+    }
+
+    fn update_plane(&mut self) {
+        let half = math::radians(self.fov) / 2.;
+        self.plane = perp(self.dir) * half.tan();
+    }
+
+    /// Sets the vertical look, clamped to [`MAX_PITCH`] either way. This
+    /// shears the view rather than tilting it, walls stay vertical.
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Looks up by `amount` screen heights, down if negative.
+    pub fn look_up(&mut self, amount: f32) {
+        self.set_pitch(self.pitch + amount);
+    }
+
+    /// Turns the view by `radians`, positive turns right.
+    pub fn rotate(&mut self, radians: f32) {
+        self.dir = glm::rotate_vec2(&self.dir, radians);
+        self.plane = glm::rotate_vec2(&self.plane, radians);
+    }
+
+    /// Camera between `self` at `t = 0` and `other` at `t = 1`, for drawing
+    /// frames between two fixed updates. Radius is taken from `self`.
+    pub fn lerp(&self, other: &Camera, t: f32) -> Camera {
+        let mut cam = self.clone();
+        cam.pos = glm::lerp(&self.pos, &other.pos, t);
+        cam.fov = self.fov + (other.fov - self.fov) * t;
+        cam.pitch = self.pitch + (other.pitch - self.pitch) * t;
+
+        let dir = glm::lerp(&self.dir, &other.dir, t);
+        // opposite directions cancel out, snap to the closer end
+        cam.dir = if glm::length2(&dir) > 1e-8 {
+            glm::normalize(&dir)
+        } else if t < 0.5 {
+            self.dir
+        } else {
+            other.dir
+        };
+        cam.update_plane();
+        cam
+    }
+
+    /// Walks `dist` along the facing direction, backwards if negative.
+    pub fn move_forward(&mut self, map: &TileMap, dist: f32) {
+        self.translate(map, self.dir * dist);
+    }
+
+    /// Walks `dist` sideways, to the right if positive.
+    pub fn strafe(&mut self, map: &TileMap, dist: f32) {
+        self.translate(map, perp(self.dir) * dist);
+    }
+
+    /// Moves by `delta`, sliding along walls that are in the way. Long moves
+    /// are split into steps no larger than the radius so walls can't be
+    /// skipped over, and cut short at the distance across the whole map. A
+    /// non-finite `delta` doesn't move the camera.
+    pub fn translate(&mut self, map: &TileMap, delta: glm::Vec2) {
+        // hypot doesn't overflow squaring long but finite moves
+        let len = delta.x.hypot(delta.y);
+        if !len.is_finite() || len == 0. {
+            return;
+        }
+        let across = map.width() as f32 + map.height() as f32 + 1.;
+        let delta = delta * (across / len).min(1.);
+
+        let max_step = self.radius.max(0.05);
+        let steps = (len.min(across) / max_step).ceil().max(1.);
+        let step = delta / steps;
+        for _ in 0..steps as u32 {
+            self.pos.x = self.resolve_axis(map, step.x, 0);
+            self.pos.y = self.resolve_axis(map, step.y, 1);
+        }
+    }
+
+    /// New coordinate along `axis` (0 = x, 1 = y) after moving by `delta`,
+    /// stopped just short of the first collider touched by the camera box.
+    /// Colliders the box already overlaps don't stop it, so it can't get
+    /// stuck in a door that closed on it.
+    fn resolve_axis(&self, map: &TileMap, delta: f32, axis: usize) -> f32 {
+        let other = 1 - axis;
+        let r = self.radius;
+        let mut target = self.pos[axis] + delta;
+        if delta == 0. {
+            return target;
+        }
+
+        // cells touched by the camera box at the end of the move
+        let mut min = self.pos - glm::vec2(r, r);
+        let mut max = self.pos + glm::vec2(r, r);
+        min[axis] = min[axis].min(target - r);
+        max[axis] = max[axis].max(target + r);
+
+        for y in min.y.floor() as i32..=max.y.floor() as i32 {
+            for x in min.x.floor() as i32..=max.x.floor() as i32 {
+                let Some((lo, hi)) = map.collider(x, y) else {
+                    continue;
+                };
+                if lo[other] >= self.pos[other] + r || hi[other] <= self.pos[other] - r {
+                    continue;
+                }
+
+                if delta > 0. {
+                    if lo[axis] >= self.pos[axis] + r && lo[axis] < target + r {
+                        target = lo[axis] - r - SKIN;
+                    }
+                } else if hi[axis] <= self.pos[axis] - r && hi[axis] > target - r {
+                    target = hi[axis] + r + SKIN;
+                }
+            }
+        }
+        target
+    }
+}
+
+/// `v` scaled to unit length, `None` if it has no direction.
+#[inline]
+fn unit(v: glm::Vec2) -> Option<glm::Vec2> {
+    let len = glm::length(&v);
+    (len.is_finite() && len > 1e-6).then(|| v / len)
+}
+
+/// `v` turned 90 degrees to the right (with y growing down).
+#[inline]
+fn perp(v: glm::Vec2) -> glm::Vec2 {
+    glm::vec2(-v.y, v.x)
+}
+
+/// Orthographic camera for the quad renderer.
+///
+/// World space y grows up. At a zoom of 1 one world unit is one pixel, and
+/// `position` is the point seen in the middle of the viewport.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera2D {
+    pub position: glm::Vec2,
+    /// Pixels per world unit.
+    pub zoom: f32,
+    /// Counter-clockwise turn of the view, in degrees.
+    pub rotation: f32,
+    /// Size of the screen area drawn to, in pixels.
+    pub viewport: glm::Vec2,
+}
+
+impl Camera2D {
+    /// Camera looking at the middle of `viewport`, so world coordinates are
+    /// pixels from its bottom left corner.
+    pub fn new(viewport: glm::Vec2) -> Self {
+        Self {
+            position: viewport / 2.,
+            zoom: 1.,
+            rotation: 0.,
+            viewport,
+        }
+    }
+
+    pub fn with_position(mut self, position: glm::Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    /// Takes world space to pixels from the middle of the viewport, y up.
+    pub fn view(&self) -> glm::Mat4 {
+        let view = glm::scale(&glm::identity(), &glm::vec3(self.zoom, self.zoom, 1.));
+        let view = glm::rotate(&view, -math::radians(self.rotation), &glm::vec3(0., 0., 1.));
+        glm::translate(&view, &-glm::vec2_to_vec3(&self.position))
+    }
+
+    /// Takes pixels from the middle of the viewport to clip space.
+    pub fn projection(&self) -> glm::Mat4 {
+        let (hw, hh) = (self.viewport.x / 2., self.viewport.y / 2.);
+        glm::ortho_rh_zo(-hw, hw, -hh, hh, -1., 1.)
+    }
+
+    pub fn view_proj(&self) -> glm::Mat4 {
+        self.projection() * self.view()
+    }
+
+    /// Point in the world under `screen`, in pixels from the viewport's top
+    /// left corner.
+    pub fn screen_to_world(&self, screen: glm::Vec2) -> glm::Vec2 {
+        let ndc = glm::vec4(
+            screen.x / self.viewport.x * 2. - 1.,
+            1. - screen.y / self.viewport.y * 2.,
+            0.,
+            1.,
+        );
+        let world = glm::inverse(&self.view_proj()) * ndc;
+        glm::vec2(world.x, world.y)
+    }
+
+    /// Where `world` is on screen, in pixels from the viewport's top left
+    /// corner.
+    pub fn world_to_screen(&self, world: glm::Vec2) -> glm::Vec2 {
+        let ndc = self.view_proj() * glm::vec4(world.x, world.y, 0., 1.);
+        glm::vec2(
+            (ndc.x + 1.) / 2. * self.viewport.x,
+            (1. - ndc.y) / 2. * self.viewport.y,
+        )
+    }
+}
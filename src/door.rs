@@ -0,0 +1,149 @@
+use nalgebra_glm as glm;
+
+/// Which way a [`ThinWall`] runs through its cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Runs along the x axis, facing north and south.
+    Horizontal,
+    /// Runs along the y axis, facing east and west.
+    Vertical,
+}
+
+/// Wall with no thickness crossing a cell from one side to the other, drawn
+/// with the cell's tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThinWall {
+    pub orientation: Orientation,
+    /// Distance from the cell's north edge (horizontal walls) or west edge
+    /// (vertical walls), in [0, 1].
+    pub offset: f32,
+}
+
+impl ThinWall {
+    pub const fn new(orientation: Orientation, offset: f32) -> Self {
+        Self {
+            orientation,
+            offset,
+        }
+    }
+
+    /// Wall through the middle of the cell.
+    pub const fn centered(orientation: Orientation) -> Self {
+        Self::new(orientation, 0.5)
+    }
+
+    /// Box covering the part of the wall from `from` to `to` along it, for a
+    /// wall in cell (x, y).
+    pub(crate) fn segment(&self, x: i32, y: i32, from: f32, to: f32) -> (glm::Vec2, glm::Vec2) {
+        let (x, y) = (x as f32, y as f32);
+        match self.orientation {
+            Orientation::Horizontal => (
+                glm::vec2(x + from, y + self.offset),
+                glm::vec2(x + to, y + self.offset),
+            ),
+            Orientation::Vertical => (
+                glm::vec2(x + self.offset, y + from),
+                glm::vec2(x + self.offset, y + to),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+/// Thin wall that slides open along itself, towards the east or south end
+/// of its cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Door {
+    pub wall: ThinWall,
+    /// Fraction of the cell opened per second.
+    pub speed: f32,
+    state: DoorState,
+    /// How far the door has slid, 0 is closed and 1 is open.
+    amount: f32,
+}
+
+impl Door {
+    /// Closed door sliding fully open in one second.
+    pub fn new(wall: ThinWall) -> Self {
+        Self {
+            wall,
+            speed: 1.,
+            state: DoorState::Closed,
+            amount: 0.,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    #[inline]
+    pub fn state(&self) -> DoorState {
+        self.state
+    }
+
+    /// How far open the door is, 0 closed to 1 open.
+    #[inline]
+    pub fn amount(&self) -> f32 {
+        self.amount
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.state == DoorState::Open
+    }
+
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.state == DoorState::Closed
+    }
+
+    /// Starts opening, does nothing if already open or opening.
+    pub fn open(&mut self) {
+        if matches!(self.state, DoorState::Closed | DoorState::Closing) {
+            self.state = DoorState::Opening;
+        }
+    }
+
+    /// Starts closing, does nothing if already closed or closing.
+    pub fn close(&mut self) {
+        if matches!(self.state, DoorState::Open | DoorState::Opening) {
+            self.state = DoorState::Closing;
+        }
+    }
+
+    /// Closes an open or opening door, opens a closed or closing one.
+    pub fn toggle(&mut self) {
+        match self.state {
+            DoorState::Open | DoorState::Opening => self.close(),
+            DoorState::Closed | DoorState::Closing => self.open(),
+        }
+    }
+
+    /// Moves the door by `dt` seconds worth of sliding.
+    pub fn update(&mut self, dt: f32) {
+        match self.state {
+            DoorState::Opening => {
+                self.amount = (self.amount + self.speed * dt).min(1.);
+                if self.amount >= 1. {
+                    self.state = DoorState::Open;
+                }
+            }
+            DoorState::Closing => {
+                self.amount = (self.amount - self.speed * dt).max(0.);
+                if self.amount <= 0. {
+                    self.state = DoorState::Closed;
+                }
+            }
+            DoorState::Open | DoorState::Closed => {}
+        }
+    }
+}
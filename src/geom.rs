@@ -1,16 +1,38 @@
 use nalgebra_glm as glm;
 
 use crate::{
-    gfx::{self, Vert2D},
+    gfx::{self, TextureHandle, Vert2D},
     math::Transform,
 };
 
+/// Axis aligned rectangle, `x` and `y` being its top left corner.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Quad {
     pub xform: Transform,
+    /// Multiplies the texture's color, or is the quad's color if it has no
+    /// texture.
     pub color: glm::Vec4,
 
     pub z_index: i32,
+
+    pub texture: Option<TextureHandle>,
+    /// Part of `texture` drawn on the quad in texels, from the texture's top
+    /// left corner. The whole texture if `None`.
+    pub src: Option<Rect>,
 }
 
 impl Quad {
@@ -37,98 +59,146 @@ impl Quad {
         },
     ];
 
-    /// Calculates/Applies self.xform and self.color to Quad verts
+    /// Calculates/Applies self.xform and self.color to Quad verts, and
+    /// self.src to their texture coordinates
     pub fn calc_into_verts(&self) -> [Vert2D; 4] {
-        todo!()
+        let model = self.xform.model();
+        let color = [self.color.x, self.color.y, self.color.z, self.color.w];
+        let mut verts = Self::VERTS.map(|v| {
+            let [x, y, z, _] = v.pos;
+            let pos = model * glm::vec4(x, y, z, 1.);
+            Vert2D {
+                pos: [pos.x, pos.y, pos.z, pos.w],
+                color,
+                uv: v.uv,
+            }
+        });
+        if let (Some(texture), Some(src)) = (self.texture, self.src) {
+            normalize_texture_coords(&mut verts, &src, texture.size());
+        }
+        verts
+    }
+}
+
+/// Range of a [`QuadBuffer`]'s indices drawn with the same texture, in one
+/// draw call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuadDraw {
+    pub texture: Option<TextureHandle>,
+    pub indices: std::ops::Range<u32>,
+}
+
+/// Quads pushed for one batch, turned into vertex and index data drawn back
+/// to front by `z_index`. Quads with the same `z_index` are grouped by
+/// texture so they can share draw calls, and otherwise drawn in the order
+/// they were pushed.
+#[derive(Debug, Clone, Default)]
+pub struct QuadBuffer {
+    quads: Vec<(QuadKey, Option<TextureHandle>, [Vert2D; 4])>,
+    vert_buf: Vec<Vert2D>,
+    index_buf: Vec<u32>,
+    draws: Vec<QuadDraw>,
+}
+
+/// What quads are sorted by, untextured quads first within a z_index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct QuadKey {
+    z_index: i32,
+    texture: Option<u32>,
+}
+
+impl QuadBuffer {
+    pub fn empty() -> Self {
+        Self {
+            quads: Vec::with_capacity(16),
+            vert_buf: Vec::with_capacity(64),
+            index_buf: Vec::with_capacity(96),
+            draws: Vec::new(),
+        }
+    }
+
+    /// Forgets every quad pushed and built so far.
+    pub fn clear(&mut self) {
+        self.quads.clear();
+        self.vert_buf.clear();
+        self.index_buf.clear();
+        self.draws.clear();
+    }
+
+    pub fn push_quad(&mut self, quad: &Quad) {
+        let key = QuadKey {
+            z_index: quad.z_index,
+            texture: quad.texture.map(|t| t.id()),
+        };
+        self.quads.push((key, quad.texture, quad.calc_into_verts()));
+    }
+
+    /// Number of quads pushed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    /// Sorts the pushed quads and fills the vertex and index buffers with two
+    /// triangles per quad, and the draws with a range of indices for every
+    /// run of quads sharing a texture.
+    pub fn build(&mut self) {
+        self.quads.sort_by_key(|quad| quad.0);
+        self.vert_buf.clear();
+        self.index_buf.clear();
+        self.draws.clear();
+        for (i, (_, texture, verts)) in self.quads.iter().enumerate() {
+            let first = i as u32 * 4;
+            self.vert_buf.extend(verts);
+            self.index_buf
+                .extend([0, 1, 2, 0, 2, 3].map(|corner| first + corner));
+
+            let end = self.index_buf.len() as u32;
+            match self.draws.last_mut() {
+                Some(draw) if draw.texture == *texture => draw.indices.end = end,
+                _ => self.draws.push(QuadDraw {
+                    texture: *texture,
+                    indices: end - 6..end,
+                }),
+            }
+        }
+    }
+
+    /// Draw calls as of the last [`QuadBuffer::build`], in order.
+    pub fn draws(&self) -> &[QuadDraw] {
+        self.draws.as_slice()
+    }
+
+    /// Vertices as of the last [`QuadBuffer::build`].
+    pub fn vertex_buffer(&self) -> &[Vert2D] {
+        self.vert_buf.as_slice()
+    }
+
+    /// Indices as of the last [`QuadBuffer::build`].
+    pub fn index_buffer(&self) -> &[u32] {
+        self.index_buf.as_slice()
+    }
+}
+
+/// Maps the unit texture coordinates of `verts` onto `texture_rect`, a part
+/// of a `texture_size` texture in texels.
+pub fn normalize_texture_coords(
+    verts: &mut [Vert2D],
+    texture_rect: &Rect,
+    texture_size: glm::Vec2,
+) {
+    let texture_rect_size = glm::vec2(texture_rect.w, texture_rect.h);
+    let uv_offset = glm::vec2(texture_rect.x, texture_rect.y);
+
+    for v in verts.iter_mut() {
+        let texture_coord =
+            texture_rect_size.component_mul(&glm::vec2(v.uv[0], v.uv[1])) + uv_offset;
+        let normalized = texture_coord.component_div(&texture_size);
+        v.uv = [normalized.x, normalized.y];
     }
 }
-//
-// #[derive(Debug, Clone)]
-// pub struct QuadBuffer {
-//     vert_buf: Vec<Vert2D>,
-//     index_buf: Vec<u32>,
-//     current: u32,
-// }
-//
-// impl QuadBuffer {
-//     pub fn empty() -> Self {
-//         Self {
-//             vert_buf: Vec::with_capacity(16),
-//             index_buf: Vec::with_capacity(32),
-//             current: 0,
-//         }
-//     }
-//
-//     pub fn push_quad(&mut self, quad: &Quad) {
-//         let verts = quad.as_verts();
-//         self.vert_buf.extend(verts);
-//
-//         self.index_buf.extend(&[
-//             self.current * 4 + 0,
-//             self.current * 4 + 1,
-//             self.current * 4 + 2,
-//             self.current * 4 + 0,
-//             self.current * 4 + 2,
-//             self.current * 4 + 3,
-//         ]);
-//         self.current += 1;
-//     }
-//
-//     pub fn push_with_xform(&mut self, quad: &Quad, xform: &nal::Matrix4<f32>) {
-//         let verts = translate_verts(quad.as_verts().as_slice(), xform);
-//         self.vert_buf.extend(verts);
-//
-//         self.index_buf.extend(&[
-//             self.current * 4 + 0,
-//             self.current * 4 + 1,
-//             self.current * 4 + 2,
-//             self.current * 4 + 0,
-//             self.current * 4 + 2,
-//             self.current * 4 + 3,
-//         ]);
-//         self.current += 1;
-//     }
-//
-//     pub fn vertex_buffer(&self) -> &[Vert2D] {
-//         self.vert_buf.as_slice()
-//     }
-//
-//     pub fn index_buffer(&self) -> &[u32] {
-//         self.index_buf.as_slice()
-//     }
-// }
-//
-// fn translate_verts(verts: &[Vert2D], xform: &nal::Matrix4<f32>) -> Vec<Vert2D> {
-//     let mut result = Vec::with_capacity(verts.len());
-//     for v in verts.iter() {
-//         let pos = nal::Vector4::new(v.pos[0], v.pos[1], v.pos[2], 1.);
-//         let new_pos = xform * pos;
-//         let new_pos = [new_pos.x, new_pos.y, new_pos.z];
-//         result.push(Vert2D::new(&new_pos, &v.uv))
-//     }
-//     result
-// }
-//
-// fn normalize_texture_coords(
-//     verts: &mut [Vert2D],
-//     texture_rect: &Rect,
-//     texture_size: &nal::Vector2<f32>,
-// ) {
-//     let texture_rect_size = nal::Vector2::new(texture_rect.w, texture_rect.h);
-//     let uv_offset = nal::Vector2::new(texture_rect.x, texture_rect.y);
-//
-//     for v in verts.iter_mut() {
-//         let flipped = nal::Vector2::new(v.uv[0], 1. - v.uv[1]);
-//
-//         let texture_dim = nal::Vector2::new(
-//             texture_rect_size.x * flipped.x,
-//             texture_rect_size.y * flipped.y,
-//         );
-//         let texture_coord = texture_dim + uv_offset;
-//         let normalized = nal::Vector2::new(
-//             texture_coord.x / texture_size.x,
-//             texture_coord.y / texture_size.y,
-//         );
-//         v.uv = [normalized.x, normalized.y];
-//     }
-// }
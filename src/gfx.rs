@@ -9,6 +9,19 @@ pub struct Vert2D {
 }
 
 impl Vert2D {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x2];
+
+    /// How vertices are laid out in a vertex buffer, matching `VertexInput`
+    /// in `shader.wgsl`.
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+
     pub fn zero() -> Self {
         Self::default()
     }
@@ -120,7 +133,7 @@ pub const fn unpack_rgb(color: u32) -> (u8, u8, u8) {
 }
 
 use anyhow::*;
-use image::{EncodableLayout, GenericImageView};
+use image::GenericImageView;
 use sdl2::{
     pixels::PixelFormatEnum,
     rect::Rect,
@@ -210,98 +223,931 @@ impl Texture {
             size,
         })
     }
+
+    /// Texture that can be drawn to and copied out of, as well as sampled.
+    pub fn render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        Self::empty(
+            device,
+            width,
+            height,
+            format,
+            wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            label,
+        )
+    }
+
+    /// Texture with undefined contents, to be filled with
+    /// [`Texture::write`] or drawn to.
+    pub fn empty(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+        label: Option<&str>,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self {
+            handle: texture,
+            view,
+            sampler,
+            size,
+        }
+    }
+
+    /// Replaces the whole texture with `bytes`, rows packed tightly in the
+    /// texture's format. The texture needs [`wgpu::TextureUsages::COPY_DST`].
+    pub fn write(&self, queue: &wgpu::Queue, bytes: &[u8]) -> anyhow::Result<()> {
+        let Some(block_size) = self.handle.format().block_size(None) else {
+            bail!("can't write to a {:?} texture", self.handle.format());
+        };
+        let row = block_size * self.size.width;
+        let expected = row as usize * self.size.height as usize;
+        if bytes.len() != expected {
+            bail!(FormatError::BufferSize {
+                expected,
+                actual: bytes.len()
+            });
+        }
+        queue.write_texture(
+            self.handle.as_image_copy(),
+            bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(row),
+                rows_per_image: None,
+            },
+            self.size,
+        );
+        Ok(())
+    }
+
+    /// Copies the texture back from the GPU, waiting for everything queued
+    /// before to finish. Only for 4 byte per pixel formats made with
+    /// [`wgpu::TextureUsages::COPY_SRC`], like [`Texture::render_target`]s.
+    pub fn read_pixels(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<image::RgbaImage> {
+        let wgpu::Extent3d { width, height, .. } = self.size;
+
+        // rows copied out of a texture must be aligned
+        let row = 4 * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = row.div_ceil(align) * align;
+        let buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("read pixels"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("read pixels"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.handle.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buf,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            self.size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buf.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = tx.send(res);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv()??;
+
+        let mut pixels = Vec::with_capacity((row * height) as usize);
+        for padded in slice.get_mapped_range().chunks(padded_row as usize) {
+            pixels.extend_from_slice(&padded[..row as usize]);
+        }
+        buf.unmap();
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("read back too few pixels"))
+    }
+}
+
+/// Refers to a [`Texture`] added to a [`crate::render::QuadRenderer`], with
+/// its size for normalizing texture coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle {
+    id: u32,
+    width: u32,
+    height: u32,
+}
+
+impl TextureHandle {
+    /// Handles are normally made by [`crate::render::QuadRenderer::add_texture`],
+    /// presenting quads drawn with one it didn't make fails with
+    /// [`crate::render::UnknownTexture`].
+    pub const fn new(id: u32, width: u32, height: u32) -> Self {
+        Self { id, width, height }
+    }
+
+    #[inline]
+    pub const fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Size of the texture in texels.
+    #[inline]
+    pub fn size(&self) -> glm::Vec2 {
+        glm::vec2(self.width as f32, self.height as f32)
+    }
+}
+
+/// CPU side RGB surface that the software raycaster draws into. Implemented
+/// for plain `image` buffers so frames can be rendered without a window, and
+/// for [`SDLTextureBuf`] to present them through SDL.
+pub trait PixelBuffer {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>);
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                self.put(x, y, color);
+            }
+        }
+    }
+
+    /// Clears to black with a white horizon line across the middle row.
+    fn clear_black(&mut self) {
+        let horizon = self.height() as i32 / 2;
+        self.clear_black_horizon(horizon);
+    }
+
+    /// Clears to black with a white horizon line across row `horizon`, if
+    /// that row is on screen.
+    fn clear_black_horizon(&mut self, horizon: i32) {
+        self.fill(image::Rgb([0, 0, 0]));
+
+        if horizon < 0 || horizon >= self.height() as i32 {
+            return;
+        }
+        for x in 0..self.width() {
+            self.put(x, horizon as u32, image::Rgb([255, 255, 255]));
+        }
+    }
+}
+
+impl PixelBuffer for image::RgbImage {
+    #[inline]
+    fn width(&self) -> u32 {
+        self.width()
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.height()
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        self.put_pixel(x, y, color);
+    }
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        for p in self.pixels_mut() {
+            *p = color;
+        }
+    }
+}
+
+impl PixelBuffer for image::RgbaImage {
+    #[inline]
+    fn width(&self) -> u32 {
+        self.width()
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.height()
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        let [r, g, b] = color.0;
+        self.put_pixel(x, y, image::Rgba([r, g, b, 255]));
+    }
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        let [r, g, b] = color.0;
+        for p in self.pixels_mut() {
+            *p = image::Rgba([r, g, b, 255]);
+        }
+    }
+}
+
+/// RGB frame stored column by column, top to bottom. Each screen column is
+/// a contiguous slice, so ranges of columns can be handed out to different
+/// threads with [`ColumnBuffer::bands_mut`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<image::Rgb<u8>>,
+}
+
+impl ColumnBuffer {
+    /// Panics if the frame has more pixels than fit in a `usize`.
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .unwrap_or_else(|| panic!("{width}x{height} frame is too large"));
+        Self {
+            width,
+            height,
+            pixels: vec![image::Rgb([0, 0, 0]); len],
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> image::Rgb<u8> {
+        self.column(x)[y as usize]
+    }
+
+    #[inline]
+    pub fn column(&self, x: u32) -> &[image::Rgb<u8>] {
+        let h = self.height as usize;
+        &self.pixels[x as usize * h..][..h]
+    }
+
+    #[inline]
+    pub fn column_mut(&mut self, x: u32) -> &mut [image::Rgb<u8>] {
+        let h = self.height as usize;
+        &mut self.pixels[x as usize * h..][..h]
+    }
+
+    /// Splits the frame into bands of `band_width` columns, left to right,
+    /// each with the x of its first column. The last band may be narrower.
+    pub fn bands_mut(&mut self, band_width: u32) -> impl Iterator<Item = (u32, ColumnBand<'_>)> {
+        let band_width = band_width.max(1);
+        let height = self.height;
+        // bands wider than the whole frame are the whole frame
+        let len = (band_width as usize).saturating_mul(height as usize).max(1);
+        self.pixels
+            .chunks_mut(len)
+            .enumerate()
+            .map(move |(i, pixels)| {
+                let band = ColumnBand {
+                    width: pixels.len() as u32 / height,
+                    height,
+                    pixels,
+                };
+                (i as u32 * band_width, band)
+            })
+    }
+
+    /// Copies the frame into `target`, which must be the same size.
+    pub fn copy_to<T: PixelBuffer + ?Sized>(&self, target: &mut T) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                target.put(x, y, self.get(x, y));
+            }
+        }
+    }
+
+    /// Copies the frame into `target`, which must be the same size, a block
+    /// of columns at a time so both buffers are read and written in order.
+    pub fn transpose_into(&self, target: &mut image::RgbImage) {
+        assert_eq!(
+            (target.width(), target.height()),
+            (self.width, self.height),
+            "transposing into a differently sized image"
+        );
+        const BLOCK: usize = 16;
+        let (w, h) = (self.width as usize, self.height as usize);
+        let out: &mut [u8] = target;
+        for x0 in (0..w).step_by(BLOCK) {
+            let x1 = (x0 + BLOCK).min(w);
+            for y in 0..h {
+                let row = &mut out[(y * w + x0) * 3..(y * w + x1) * 3];
+                for (x, px) in (x0..x1).zip(row.chunks_exact_mut(3)) {
+                    px.copy_from_slice(&self.pixels[x * h + y].0);
+                }
+            }
+        }
+    }
+
+    pub fn to_rgb_image(&self) -> image::RgbImage {
+        let mut img = image::RgbImage::new(self.width, self.height);
+        self.transpose_into(&mut img);
+        img
+    }
+}
+
+impl PixelBuffer for ColumnBuffer {
+    #[inline]
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        self.column_mut(x)[y as usize] = color;
+    }
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        self.pixels.fill(color);
+    }
+}
+
+/// Range of whole columns borrowed from a [`ColumnBuffer`]. Coordinates are
+/// relative to the band's first column.
+#[derive(Debug)]
+pub struct ColumnBand<'a> {
+    width: u32,
+    height: u32,
+    pixels: &'a mut [image::Rgb<u8>],
+}
+
+impl PixelBuffer for ColumnBand<'_> {
+    #[inline]
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        debug_assert!(y < self.height);
+        self.pixels[x as usize * self.height as usize + y as usize] = color;
+    }
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        self.pixels.fill(color);
+    }
+}
+
+/// Order the pixels of a [`FrameBuf`] are kept in while drawing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Drawn straight into the rows uploaded to the texture.
+    #[default]
+    RowMajor,
+    /// Drawn into a [`ColumnBuffer`], so vertical lines are contiguous in
+    /// memory, and transposed into rows once per flush.
+    ColumnMajor,
+}
+
+/// How a software rendered frame is fitted to the window it's shown in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// Rendered at the window's size, following it as it's resized.
+    #[default]
+    Native,
+    /// Rendered at a fixed resolution, scaled up by the largest whole number
+    /// that fits the window and centered. Never scaled down.
+    Integer { width: u32, height: u32 },
+    /// Rendered at a fixed resolution, stretched to fill as much of the
+    /// window as fits without changing its aspect ratio and centered.
+    Letterbox { width: u32, height: u32 },
+}
+
+impl Scaling {
+    /// Size to render frames at in a `window` sized window.
+    pub fn resolution(&self, window: (u32, u32)) -> (u32, u32) {
+        match *self {
+            Self::Native => window,
+            Self::Integer { width, height } | Self::Letterbox { width, height } => (width, height),
+        }
+    }
+
+    /// Part of a `window` sized window that frames are drawn over.
+    pub fn viewport(&self, window: (u32, u32)) -> Rect {
+        let (ww, wh) = window;
+        let (w, h) = match *self {
+            Self::Native => window,
+            Self::Integer { width, height } => {
+                let scale = (ww / width.max(1)).min(wh / height.max(1)).max(1);
+                (width * scale, height * scale)
+            }
+            Self::Letterbox { width, height } => {
+                let scale = (ww as f32 / width.max(1) as f32).min(wh as f32 / height.max(1) as f32);
+                (
+                    (width as f32 * scale).round() as u32,
+                    (height as f32 * scale).round() as u32,
+                )
+            }
+        };
+        let x = (ww as i32 - w as i32) / 2;
+        let y = (wh as i32 - h as i32) / 2;
+        Rect::new(x, y, w, h)
+    }
+}
+
+/// Pixel format of the texture behind a [`SDLTextureBuf`]. The 32 bit
+/// formats are packed into native endian `u32`s, the same as SDL does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// Three bytes per pixel, red, green and blue in memory order.
+    Rgb24,
+    /// Packed as `0xRRGGBBAA`, see [`pack_rgba`].
+    Rgba8888,
+    /// Packed as `0xAARRGGBB`.
+    #[default]
+    Argb8888,
+}
+
+/// A pixel buffer that doesn't fit the size and [`TextureFormat`] it's
+/// converted with.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FormatError {
+    #[error("pitch of {pitch} bytes is less than a {width} pixel row of {format:?}")]
+    PitchTooSmall {
+        format: TextureFormat,
+        width: u32,
+        pitch: usize,
+    },
+    #[error("pixel buffer is {actual} bytes, {expected} needed")]
+    BufferSize { expected: usize, actual: usize },
+}
+
+impl TextureFormat {
+    #[inline]
+    pub const fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Rgb24 => 3,
+            Self::Rgba8888 | Self::Argb8888 => 4,
+        }
+    }
+
+    /// Bytes in a tightly packed row of `width` pixels.
+    #[inline]
+    pub const fn pitch(&self, width: u32) -> usize {
+        width as usize * self.bytes_per_pixel()
+    }
+
+    pub fn sdl_format(&self) -> PixelFormatEnum {
+        match self {
+            Self::Rgb24 => PixelFormatEnum::RGB24,
+            Self::Rgba8888 => PixelFormatEnum::RGBA8888,
+            Self::Argb8888 => PixelFormatEnum::ARGB8888,
+        }
+    }
+
+    /// Writes `color`, fully opaque, into the first
+    /// [`TextureFormat::bytes_per_pixel`] bytes of `out`.
+    #[inline]
+    pub fn encode(&self, color: image::Rgb<u8>, out: &mut [u8]) {
+        let [r, g, b] = color.0;
+        match self {
+            Self::Rgb24 => out[..3].copy_from_slice(&color.0),
+            Self::Rgba8888 => out[..4].copy_from_slice(&pack_rgba(r, g, b, 255).to_ne_bytes()),
+            Self::Argb8888 => {
+                let argb = pack_rgba(r, g, b, 255).rotate_right(8);
+                out[..4].copy_from_slice(&argb.to_ne_bytes())
+            }
+        }
+    }
+
+    /// Reads the color of the pixel at the start of `bytes`, dropping alpha.
+    #[inline]
+    pub fn decode(&self, bytes: &[u8]) -> image::Rgb<u8> {
+        let packed = |bytes: &[u8]| u32::from_ne_bytes(bytes[..4].try_into().unwrap());
+        let (r, g, b) = match self {
+            Self::Rgb24 => (bytes[0], bytes[1], bytes[2]),
+            Self::Rgba8888 => unpack_rgb(packed(bytes)),
+            Self::Argb8888 => unpack_rgb(packed(bytes).rotate_left(8)),
+        };
+        image::Rgb([r, g, b])
+    }
+
+    /// Checks that `len` bytes hold `width` x `height` pixels in rows
+    /// `pitch` bytes apart. The last row doesn't need padding.
+    pub fn check_buffer(
+        &self,
+        width: u32,
+        height: u32,
+        pitch: usize,
+        len: usize,
+    ) -> Result<(), FormatError> {
+        let row = self.pitch(width);
+        if pitch < row {
+            return Err(FormatError::PitchTooSmall {
+                format: *self,
+                width,
+                pitch,
+            });
+        }
+        let expected = match height {
+            0 => 0,
+            h => pitch * (h as usize - 1) + row,
+        };
+        if len < expected {
+            return Err(FormatError::BufferSize {
+                expected,
+                actual: len,
+            });
+        }
+        Result::Ok(())
+    }
+
+    /// Converts `image` into `out`, rows `pitch` bytes apart.
+    pub fn encode_image(
+        &self,
+        image: &image::RgbImage,
+        out: &mut [u8],
+        pitch: usize,
+    ) -> Result<(), FormatError> {
+        let (w, h) = image.dimensions();
+        self.check_buffer(w, h, pitch, out.len())?;
+        let bpp = self.bytes_per_pixel();
+        for (src, dst) in image.rows().zip(out.chunks_mut(pitch)) {
+            for (px, out) in src.zip(dst.chunks_exact_mut(bpp)) {
+                self.encode(*px, out);
+            }
+        }
+        Result::Ok(())
+    }
+
+    /// Reads a `width` x `height` image out of `bytes`, rows `pitch` bytes
+    /// apart.
+    pub fn decode_image(
+        &self,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        pitch: usize,
+    ) -> Result<image::RgbImage, FormatError> {
+        self.check_buffer(width, height, pitch, bytes.len())?;
+        let bpp = self.bytes_per_pixel();
+        Result::Ok(image::RgbImage::from_fn(width, height, |x, y| {
+            self.decode(&bytes[y as usize * pitch + x as usize * bpp..])
+        }))
+    }
+}
+
+/// Software rendered frame, in either [`Layout`]. What [`SDLTextureBuf`] and
+/// [`WgpuTextureBuf`] draw into before uploading it to their texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBuf {
+    pixels: image::RgbImage,
+    /// What's drawn to in [`Layout::ColumnMajor`].
+    columns: Option<ColumnBuffer>,
+}
+
+impl FrameBuf {
+    pub fn new(w: u32, h: u32) -> Self {
+        Self {
+            pixels: image::RgbImage::new(w, h),
+            columns: None,
+        }
+    }
+
+    pub fn with_layout(w: u32, h: u32, layout: Layout) -> Self {
+        let mut s = Self::new(w, h);
+        s.set_layout(layout);
+        s
+    }
+
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        match self.columns {
+            Some(_) => Layout::ColumnMajor,
+            None => Layout::RowMajor,
+        }
+    }
+
+    /// Switches layouts, keeping what has been drawn so far.
+    pub fn set_layout(&mut self, layout: Layout) {
+        match (layout, &self.columns) {
+            (Layout::ColumnMajor, None) => {
+                let mut columns = ColumnBuffer::new(self.width(), self.height());
+                for (x, y, &px) in self.pixels.enumerate_pixels() {
+                    columns.put(x, y, px);
+                }
+                self.columns = Some(columns);
+            }
+            (Layout::RowMajor, Some(columns)) => {
+                columns.transpose_into(&mut self.pixels);
+                self.columns = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Column-major buffer being drawn into, `None` in [`Layout::RowMajor`].
+    #[inline]
+    pub fn columns_mut(&mut self) -> Option<&mut ColumnBuffer> {
+        self.columns.as_mut()
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.pixels.width()
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.pixels.height()
+    }
+
+    pub fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        if let Some(columns) = &mut self.columns {
+            columns.put(x, y, color);
+            return;
+        }
+        self.pixels.put_pixel(x, y, color);
+    }
+
+    /// The frame in rows, transposing the columns drawn in
+    /// [`Layout::ColumnMajor`] first.
+    pub fn rows(&mut self) -> &image::RgbImage {
+        if let Some(columns) = &self.columns {
+            columns.transpose_into(&mut self.pixels);
+        }
+        &self.pixels
+    }
+}
+
+impl PixelBuffer for FrameBuf {
+    #[inline]
+    fn width(&self) -> u32 {
+        self.width()
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.height()
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        self.put(x, y, color);
+    }
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        match &mut self.columns {
+            Some(columns) => columns.fill(color),
+            None => PixelBuffer::fill(&mut self.pixels, color),
+        }
+    }
 }
 
 /// Software rendered Texture Surface with an underlying SDL_Texture that is
-/// written to for drawing. all writing to texture happens when flush() is called
+/// written to for drawing. all writing to texture happens when flush() is called,
+/// converting the frame into the texture's [`TextureFormat`].
 ///
 /// type: Streaming,
-/// format: RGB888 (With alpha padding),
+/// format: [`TextureFormat::Argb8888`] unless made with [`SDLTextureBuf::with_format`],
 ///
 pub struct SDLTextureBuf {
     tex: sdl2::render::Texture,
-    pixels: image::ImageBuffer<image::Rgb<u8>, Vec<u8>>,
+    format: TextureFormat,
+    frame: FrameBuf,
+    /// `frame` converted to `format`, uploaded on flush.
+    bytes: Vec<u8>,
 }
 
 impl SDLTextureBuf {
     pub fn new(tc: &TextureCreator<WindowContext>, w: u32, h: u32) -> anyhow::Result<Self> {
-        let tex = tc.create_texture_streaming(PixelFormatEnum::RGB888, w as u32, h as u32)?;
-        // let pixels = vec![0; w * h * 4];
-        let buf = image::DynamicImage::new_rgb8(w, h);
-        let pixels = buf.to_rgb8();
-        let s = Self { tex, pixels };
+        Self::with_format(tc, w, h, TextureFormat::default())
+    }
+
+    pub fn with_format(
+        tc: &TextureCreator<WindowContext>,
+        w: u32,
+        h: u32,
+        format: TextureFormat,
+    ) -> anyhow::Result<Self> {
+        let tex = tc.create_texture_streaming(format.sdl_format(), w, h)?;
+        let s = Self {
+            tex,
+            format,
+            frame: FrameBuf::new(w, h),
+            bytes: vec![0; format.pitch(w) * h as usize],
+        };
+        Ok(s)
+    }
+
+    pub fn with_layout(
+        tc: &TextureCreator<WindowContext>,
+        w: u32,
+        h: u32,
+        layout: Layout,
+    ) -> anyhow::Result<Self> {
+        let mut s = Self::new(tc, w, h)?;
+        s.set_layout(layout);
         Ok(s)
     }
 
     #[inline]
-    pub fn pitch(&self) -> u32 {
-        self.width()
+    pub fn format(&self) -> TextureFormat {
+        self.format
     }
 
     #[inline]
-    pub fn width(&self) -> u32 {
-        self.pixels.width()
+    pub fn frame(&self) -> &FrameBuf {
+        &self.frame
     }
 
     #[inline]
-    pub fn height(&self) -> u32 {
-        self.pixels.height()
+    pub fn frame_mut(&mut self) -> &mut FrameBuf {
+        &mut self.frame
     }
 
-    // pub const fn index(&self, x: usize, y: usize) -> usize {
-    //     let pitch = self.pitch() as usize;
-    //     y * pitch + x * 4
-    // }
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.frame.layout()
+    }
 
-    // #[inline]
-    // pub fn byte_at(&self, x: usize, y: usize) -> u8 {
-    //     self.pixels[self.index(x, y)]
-    // }
+    /// Switches layouts, keeping what has been drawn so far.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.frame.set_layout(layout);
+    }
 
-    // #[inline]
-    // pub fn pixel(&self, x: usize, y: usize) -> sdl2::pixels::Color {
-    //     let i = self.index(x, y);
-    //     let r = self.pixels[i];
-    //     let g = self.pixels[i + 1];
-    //     let b = self.pixels[i + 2];
-    //     sdl2::pixels::Color::RGB(r, g, b)
-    // }
+    /// Column-major buffer being drawn into, `None` in [`Layout::RowMajor`].
+    #[inline]
+    pub fn columns_mut(&mut self) -> Option<&mut ColumnBuffer> {
+        self.frame.columns_mut()
+    }
+
+    /// Bytes per row of the texture.
+    #[inline]
+    pub fn pitch(&self) -> usize {
+        self.format.pitch(self.width())
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.frame.width()
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.frame.height()
+    }
 
     pub fn flush(&mut self) -> anyhow::Result<()> {
-        // let bytes = self.pixels.as_flat_samples();
-        self.tex
-            .update(None, self.pixels.as_bytes(), self.pitch() as _)?;
+        let pitch = self.pitch();
+        self.format
+            .encode_image(self.frame.rows(), &mut self.bytes, pitch)?;
+        self.tex.update(None, &self.bytes, pitch)?;
 
         Ok(())
     }
 
+    #[inline]
     pub fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
-        self.pixels.put_pixel(x, y, color);
-        // let i = self.index(x, y);
-        //
-        // self.pixels[i] = color.r;
-        // self.pixels[i + 1] = color.g;
-        // self.pixels[i + 2] = color.b;
+        self.frame.put(x, y, color);
+    }
+
+    /// Copies the last flushed frame onto `canvas`, stretched over `dst` or
+    /// the whole canvas if `None`.
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        dst: impl Into<Option<Rect>>,
+    ) -> anyhow::Result<()> {
+        canvas.copy(&self.tex, None, dst).map_err(|e| anyhow!(e))?;
+        Ok(())
     }
+}
 
-    pub fn clear_black(&mut self) {
-        for p in self.pixels.iter_mut() {
-            *p = 0;
+impl PixelBuffer for SDLTextureBuf {
+    #[inline]
+    fn width(&self) -> u32 {
+        self.width()
+    }
+
+    #[inline]
+    fn height(&self) -> u32 {
+        self.height()
+    }
+
+    #[inline]
+    fn put(&mut self, x: u32, y: u32, color: image::Rgb<u8>) {
+        self.put(x, y, color);
+    }
+
+    fn fill(&mut self, color: image::Rgb<u8>) {
+        self.frame.fill(color);
+    }
+}
+
+/// Software rendered frame shown through wgpu, the counterpart of
+/// [`SDLTextureBuf`]. Drawn to on the CPU, then uploaded into a [`Texture`]
+/// made with [`WgpuTextureBuf::create_texture`] when flushed.
+pub struct WgpuTextureBuf {
+    frame: FrameBuf,
+    /// `frame` as RGBA, uploaded on flush.
+    bytes: Vec<u8>,
+}
+
+impl WgpuTextureBuf {
+    pub fn new(w: u32, h: u32) -> Self {
+        Self {
+            frame: FrameBuf::new(w, h),
+            bytes: vec![0; 4 * w as usize * h as usize],
         }
+    }
 
-        for x in 0..self.width() {
-            self.put(x, self.height() / 2, image::Rgb([255, 255, 255]));
+    /// Format of the texture frames are uploaded to when it's drawn onto a
+    /// `target` formatted texture. The raycaster's colors are sRGB already,
+    /// so they're decoded when sampled only if the target encodes them
+    /// again, and are passed through as they are otherwise.
+    pub fn texture_format(target: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        if target.is_srgb() {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
         }
     }
 
-    pub fn draw(&self, canvas: &mut Canvas<Window>) -> anyhow::Result<()> {
-        canvas
-            .copy(
-                &self.tex, None,
-                None,
-                // Rect::new(0, 0, self.w as u32 / 2, self.h as u32 / 2),
-            )
-            .map_err(|e| anyhow!(e))?;
-        Ok(())
+    /// Texture the frame can be flushed to, to be drawn onto `target`
+    /// formatted textures.
+    pub fn create_texture(&self, device: &wgpu::Device, target: wgpu::TextureFormat) -> Texture {
+        Texture::empty(
+            device,
+            self.width(),
+            self.height(),
+            Self::texture_format(target),
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            Some("software frame"),
+        )
+    }
+
+    #[inline]
+    pub fn frame(&self) -> &FrameBuf {
+        &self.frame
+    }
+
+    #[inline]
+    pub fn frame_mut(&mut self) -> &mut FrameBuf {
+        &mut self.frame
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.frame.width()
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.frame.height()
+    }
+
+    /// Uploads the frame into `texture` with `queue.write_texture`.
+    pub fn flush(&mut self, queue: &wgpu::Queue, texture: &Texture) -> anyhow::Result<()> {
+        for (px, out) in self
+            .frame
+            .rows()
+            .pixels()
+            .zip(self.bytes.chunks_exact_mut(4))
+        {
+            let [r, g, b] = px.0;
+            out.copy_from_slice(&[r, g, b, 255]);
+        }
+        texture.write(queue, &self.bytes)
     }
 }
@@ -0,0 +1,413 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    atlas::TextureAtlas,
+    camera::Camera,
+    door::Orientation,
+    gfx::{Texture, TextureType},
+    light::{Fog, Lighting},
+    map::{Tile, TileMap},
+    raycast::wall_color,
+    shader::RaycastGlobals,
+};
+
+/// Format [`GpuRaycaster::render_frame`] renders to. Not sRGB, so colors
+/// come out with the same bytes the software raycaster writes.
+pub const FRAME_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Raycaster running on the GPU, drawing the same walls, floors, ceilings,
+/// fog and light levels as [`crate::raycast::Raycaster`].
+///
+/// The map is uploaded as a texture with one texel per cell and every pixel
+/// of the frame casts the ray of its column in `raycast.wgsl`. Frames match
+/// the software raycaster up to float rounding. Sprites are only drawn by
+/// the software raycaster.
+///
+/// The map and atlas are not tracked, upload them again with
+/// [`GpuRaycaster::upload_map`] and [`GpuRaycaster::set_atlas`] when they
+/// change, e.g. after doors move.
+pub struct GpuRaycaster {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    uniform_buf: wgpu::Buffer,
+    /// Color of untextured walls for every tile id, written once.
+    palette_buf: wgpu::Buffer,
+    cells: Texture,
+    /// 256 x 1, the atlas region of every tile id.
+    regions: Texture,
+    atlas: Texture,
+    lighting: Lighting,
+}
+
+impl GpuRaycaster {
+    /// Raycaster drawing to targets in `format`, starting out with an empty
+    /// 1x1 map and no atlas.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Self> {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("raycast"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "raycast.wgsl"
+            ))),
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("raycast globals"),
+            size: RaycastGlobals::buffer_size(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let palette_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("raycast palette"),
+            size: 256 * 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut palette = Vec::with_capacity(256 * 16);
+        for id in 0..=u8::MAX {
+            let [r, g, b] = wall_color(Tile(id));
+            let texel = [r as u32, g as u32, b as u32, 0];
+            palette.extend(texel.iter().flat_map(|v| v.to_ne_bytes()));
+        }
+        queue.write_buffer(&palette_buf, 0, &palette);
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("raycast"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1, wgpu::TextureSampleType::Uint),
+                texture_entry(2, wgpu::TextureSampleType::Uint),
+                // texels are loaded, never filtered
+                texture_entry(3, wgpu::TextureSampleType::Float { filterable: false }),
+                uniform_entry(4),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("raycast"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("raycast"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let cells = cell_texture(device, 1, 1);
+        let regions = data_texture(device, 256, 1, "atlas regions");
+        let atlas = empty_atlas(device, queue)?;
+        let bind_group = bind_group(
+            device,
+            &layout,
+            &uniform_buf,
+            &palette_buf,
+            &cells,
+            &regions,
+            &atlas,
+        );
+
+        let mut s = Self {
+            pipeline,
+            layout,
+            bind_group,
+            uniform_buf,
+            palette_buf,
+            cells,
+            regions,
+            atlas,
+            lighting: Lighting::default(),
+        };
+        s.upload_map(device, queue, &TileMap::new(1, 1))?;
+        s.write_regions(queue, None)?;
+        Ok(s)
+    }
+
+    /// Uploads every cell of `map`, to be drawn until the next upload.
+    pub fn upload_map(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        map: &TileMap,
+    ) -> anyhow::Result<()> {
+        let (w, h) = (map.width().max(1), map.height().max(1));
+        if (self.cells.size.width, self.cells.size.height) != (w, h) {
+            self.cells = cell_texture(device, w, h);
+            self.rebind(device);
+        }
+
+        let mut bytes = Vec::with_capacity(w as usize * h as usize * 16);
+        for y in 0..h as i32 {
+            for x in 0..w as i32 {
+                bytes.extend(encode_cell(map, x, y).iter().flat_map(|v| v.to_ne_bytes()));
+            }
+        }
+        self.cells.write(queue, &bytes)
+    }
+
+    /// Uploads `atlas` and its regions, walls are drawn with flat colors
+    /// and floors and ceilings not at all without one.
+    pub fn set_atlas(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        atlas: Option<&TextureAtlas>,
+    ) -> anyhow::Result<()> {
+        self.atlas = match atlas {
+            Some(atlas) => Texture::from_image(
+                device,
+                queue,
+                &image::DynamicImage::ImageRgba8(atlas.image().clone()),
+                TextureType::Normal,
+                Some("raycast atlas"),
+            )?,
+            None => empty_atlas(device, queue)?,
+        };
+        self.rebind(device);
+        self.write_regions(queue, atlas)
+    }
+
+    fn write_regions(
+        &self,
+        queue: &wgpu::Queue,
+        atlas: Option<&TextureAtlas>,
+    ) -> anyhow::Result<()> {
+        let mut bytes = Vec::with_capacity(256 * 16);
+        for id in 0..=u8::MAX {
+            let region = atlas.and_then(|atlas| atlas.region(Tile(id)));
+            let texel = region.map_or([0; 4], |r| [r.x, r.y, r.w, r.h]);
+            bytes.extend(texel.iter().flat_map(|v| v.to_ne_bytes()));
+        }
+        self.regions.write(queue, &bytes)
+    }
+
+    #[inline]
+    pub fn lighting(&self) -> &Lighting {
+        &self.lighting
+    }
+
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        self.lighting = lighting;
+    }
+
+    /// Records a pass drawing the map seen from `cam` over all of `view`,
+    /// a `width` x `height` target in the format the raycaster was made
+    /// for. The whole target is drawn, cleared the same way
+    /// [`crate::raycast::Raycaster::render_frame`] clears its frame.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        cam: &Camera,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<()> {
+        let globals = self.globals(cam, width, height);
+        queue.write_buffer(&self.uniform_buf, 0, &globals.as_bytes()?);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("raycast"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+
+    /// Renders a single frame into a new `width` x `height` image, the GPU
+    /// version of [`crate::raycast::Raycaster::render_frame`]. The
+    /// raycaster must have been made for [`FRAME_FORMAT`].
+    pub fn render_frame(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cam: &Camera,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<image::RgbImage> {
+        let target = Texture::render_target(device, width, height, FRAME_FORMAT, Some("frame"));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("raycast frame"),
+        });
+        self.render(queue, &mut encoder, &target.view, cam, width, height)?;
+        queue.submit(Some(encoder.finish()));
+
+        let rgba = target.read_pixels(device, queue)?;
+        Ok(image::DynamicImage::ImageRgba8(rgba).to_rgb8())
+    }
+
+    fn globals(&self, cam: &Camera, width: u32, height: u32) -> RaycastGlobals {
+        let (fog_kind, fog_params) = match self.lighting.fog {
+            Fog::None => (0, glm::vec2(0., 0.)),
+            Fog::Linear { start, end } => (1, glm::vec2(start, end)),
+            Fog::Exponential { density } => (2, glm::vec2(density, 0.)),
+        };
+        let [r, g, b] = self.lighting.fog_color.0;
+        RaycastGlobals {
+            pos: cam.pos(),
+            dir: cam.dir(),
+            plane: cam.plane(),
+            size: glm::vec2(width, height),
+            map_size: glm::vec2(self.cells.size.width, self.cells.size.height),
+            horizon: cam.horizon(height),
+            fog_kind,
+            fog_params,
+            ambient: self.lighting.ambient,
+            fog_color: glm::vec3(r as f32, g as f32, b as f32),
+        }
+    }
+
+    fn rebind(&mut self, device: &wgpu::Device) {
+        self.bind_group = bind_group(
+            device,
+            &self.layout,
+            &self.uniform_buf,
+            &self.palette_buf,
+            &self.cells,
+            &self.regions,
+            &self.atlas,
+        );
+    }
+}
+
+/// Texel of cell (x, y) in `raycast.wgsl`'s `cells` texture: the wall, floor
+/// and ceiling tiles and the shape of thin walls and doors packed into red,
+/// then the bits of the light level, thin wall offset and door amount.
+fn encode_cell(map: &TileMap, x: i32, y: i32) -> [u32; 4] {
+    let id = |tile: Option<Tile>| tile.unwrap_or_default().id() as u32;
+    let (shape, wall, amount) = match (map.door(x, y), map.thin_wall(x, y)) {
+        (Some(door), _) => (2, Some(door.wall), door.amount()),
+        (None, Some(wall)) => (1, Some(wall), 0.),
+        (None, None) => (0, None, 0.),
+    };
+    let vertical = wall.is_some_and(|w| w.orientation == Orientation::Vertical) as u32;
+    let offset = wall.map_or(0., |w| w.offset);
+
+    [
+        id(map.get(x, y))
+            | id(map.floor(x, y)) << 8
+            | id(map.ceiling(x, y)) << 16
+            | shape << 24
+            | vertical << 26,
+        map.light(x, y).unwrap_or(1.).to_bits(),
+        offset.to_bits(),
+        amount.to_bits(),
+    ]
+}
+
+fn data_texture(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Texture {
+    Texture::empty(
+        device,
+        width,
+        height,
+        wgpu::TextureFormat::Rgba32Uint,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        Some(label),
+    )
+}
+
+fn cell_texture(device: &wgpu::Device, width: u32, height: u32) -> Texture {
+    data_texture(device, width, height, "map cells")
+}
+
+/// Stands in for the atlas when there is none, never read as no tile has a
+/// region.
+fn empty_atlas(device: &wgpu::Device, queue: &wgpu::Queue) -> anyhow::Result<Texture> {
+    Texture::from_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(image::RgbaImage::new(1, 1)),
+        TextureType::Normal,
+        Some("no atlas"),
+    )
+}
+
+fn bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buf: &wgpu::Buffer,
+    palette_buf: &wgpu::Buffer,
+    cells: &Texture,
+    regions: &Texture,
+    atlas: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("raycast"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buf.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&cells.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&regions.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&atlas.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: palette_buf.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use sdl2::{
+    keyboard::{KeyboardState, Scancode},
+    mouse::RelativeMouseState,
+};
+
+use crate::camera::Camera;
+
+/// Something the player can do by holding a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    TurnLeft,
+    TurnRight,
+    StrafeLeft,
+    StrafeRight,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+    ];
+
+    #[inline]
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Rebindable table of which keys trigger which [`Action`]. Keys are bound by
+/// scancode so bindings follow key position, not keyboard layout. Any number
+/// of keys can trigger the same action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMap {
+    bindings: HashMap<Scancode, Action>,
+}
+
+impl InputMap {
+    /// Map with no bindings at all.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Binds `key` to `action`, replacing what `key` was bound to before.
+    pub fn bind(&mut self, key: Scancode, action: Action) -> &mut Self {
+        self.bindings.insert(key, action);
+        self
+    }
+
+    pub fn unbind(&mut self, key: Scancode) -> Option<Action> {
+        self.bindings.remove(&key)
+    }
+
+    /// Removes every key bound to `action`.
+    pub fn unbind_action(&mut self, action: Action) {
+        self.bindings.retain(|_, a| *a != action);
+    }
+
+    #[inline]
+    pub fn action(&self, key: Scancode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = Scancode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, a)| **a == action)
+            .map(|(k, _)| *k)
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (Scancode, Action)> + '_ {
+        self.bindings.iter().map(|(k, a)| (*k, *a))
+    }
+}
+
+impl Default for InputMap {
+    /// Arrow keys to move and turn, WASD to move and strafe.
+    fn default() -> Self {
+        let mut map = Self::empty();
+        map.bind(Scancode::Up, Action::MoveForward)
+            .bind(Scancode::Down, Action::MoveBack)
+            .bind(Scancode::Left, Action::TurnLeft)
+            .bind(Scancode::Right, Action::TurnRight)
+            .bind(Scancode::W, Action::MoveForward)
+            .bind(Scancode::S, Action::MoveBack)
+            .bind(Scancode::A, Action::StrafeLeft)
+            .bind(Scancode::D, Action::StrafeRight);
+        map
+    }
+}
+
+/// Which actions are held this frame. Sample it once per frame, after
+/// polling events, and scale movement by the frame time so speed does not
+/// depend on frame rate or key repeat.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InputState {
+    held: [bool; Action::ALL.len()],
+    /// Mouse movement since the last sample, in pixels.
+    mouse_delta: (i32, i32),
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Samples held keys from SDL's keyboard state.
+    pub fn sample(&mut self, map: &InputMap, keyboard: &KeyboardState) {
+        self.sample_with(map, |key| keyboard.is_scancode_pressed(key));
+    }
+
+    /// Samples held keys through `is_pressed`, for when keys don't come from
+    /// SDL's keyboard state.
+    pub fn sample_with(&mut self, map: &InputMap, is_pressed: impl Fn(Scancode) -> bool) {
+        self.held = Default::default();
+        for (key, action) in map.bindings() {
+            if is_pressed(key) {
+                self.held[action.index()] = true;
+            }
+        }
+    }
+
+    /// Samples mouse movement since the last call. Needs relative mouse mode
+    /// to keep reporting movement once the cursor hits the window edge.
+    pub fn sample_mouse(&mut self, mouse: &RelativeMouseState) {
+        self.sample_mouse_with(mouse.x(), mouse.y());
+    }
+
+    pub fn sample_mouse_with(&mut self, dx: i32, dy: i32) {
+        self.mouse_delta = (dx, dy);
+    }
+
+    /// Mouse movement since the last sample, in pixels. y grows down.
+    #[inline]
+    pub fn mouse_delta(&self) -> (i32, i32) {
+        self.mouse_delta
+    }
+
+    #[inline]
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held[action.index()]
+    }
+
+    /// -1 if only `neg` is held, 1 if only `pos` is held, 0 otherwise.
+    #[inline]
+    pub fn axis(&self, neg: Action, pos: Action) -> f32 {
+        self.is_held(pos) as i32 as f32 - self.is_held(neg) as i32 as f32
+    }
+
+    /// Forward/back movement, positive is forward.
+    #[inline]
+    pub fn forward(&self) -> f32 {
+        self.axis(Action::MoveBack, Action::MoveForward)
+    }
+
+    /// Turning, positive is to the right.
+    #[inline]
+    pub fn turn(&self) -> f32 {
+        self.axis(Action::TurnLeft, Action::TurnRight)
+    }
+
+    /// Sideways movement, positive is to the right.
+    #[inline]
+    pub fn strafe(&self) -> f32 {
+        self.axis(Action::StrafeLeft, Action::StrafeRight)
+    }
+}
+
+/// Turns mouse movement into camera rotation and, optionally, pitch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MouseLook {
+    /// Radians turned per pixel of horizontal movement.
+    pub sensitivity: f32,
+    /// Screen heights of pitch per pixel of vertical movement.
+    pub pitch_sensitivity: f32,
+    /// Moves the horizon with vertical mouse movement when set.
+    pub vertical: bool,
+    pub invert_x: bool,
+    /// When set, moving the mouse up looks down.
+    pub invert_y: bool,
+}
+
+impl Default for MouseLook {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.003,
+            pitch_sensitivity: 0.002,
+            vertical: false,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+impl MouseLook {
+    pub fn apply(&self, cam: &mut Camera, input: &InputState) {
+        let (dx, dy) = input.mouse_delta();
+
+        let x_sign = if self.invert_x { -1. } else { 1. };
+        cam.rotate(dx as f32 * self.sensitivity * x_sign);
+
+        if self.vertical {
+            // mouse y grows down, moving it up looks up unless inverted
+            let y_sign = if self.invert_y { 1. } else { -1. };
+            cam.look_up(dy as f32 * self.pitch_sensitivity * y_sign);
+        }
+    }
+}
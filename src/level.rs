@@ -0,0 +1,516 @@
+//! Level files: a [`TileMap`] drawn as ASCII art plus the metadata needed to
+//! play it.
+//!
+//! ```text
+//! ; comments start with ';'
+//! [level]
+//! name = Demo
+//! facing = north          ; north/south/east/west or an `x y` vector
+//! atlas = textures.png    ; relative to the level file
+//! floor = 5               ; floor and ceiling of every cell, optional
+//! ceiling = 6
+//! spawn = 2.5 2.5         ; or use a spawn tile in the grid
+//!
+//! [tiles]
+//! # = 1                   ; symbol = wall id
+//! B = 2
+//! . = 0
+//! , = 0 floor 7 ceiling 0 ; overrides the level's floor/ceiling
+//! P = spawn               ; empty cell, the player starts in its middle
+//!
+//! [textures]
+//! 1 = 0 0 64 64           ; tile id = x y w h in the atlas image
+//! 2 = 64 0 64 64
+//!
+//! [grid]
+//! #####
+//! #P.,#
+//! #.B.#
+//! #####
+//! ```
+//!
+//! `[grid]` must come last, every line after it is a row of the map. All rows
+//! must have the same length and the outer border must be made of walls.
+
+use std::{collections::HashMap, fmt::Display, path::Path, path::PathBuf, str::FromStr};
+
+use nalgebra_glm as glm;
+use thiserror::Error;
+
+use crate::{
+    atlas::{AtlasRegion, TextureAtlas},
+    camera::Camera,
+    map::{Tile, TileMap},
+    sprite::Sprite,
+};
+
+/// Unit direction for `north`, `south`, `east` or `west`. North is up the
+/// map, towards y = 0.
+pub(crate) fn compass(name: &str) -> Option<glm::Vec2> {
+    match name {
+        "north" => Some(glm::vec2(0., -1.)),
+        "south" => Some(glm::vec2(0., 1.)),
+        "east" => Some(glm::vec2(1., 0.)),
+        "west" => Some(glm::vec2(-1., 0.)),
+        _ => None,
+    }
+}
+
+/// Why a level file could not be loaded. Lines and columns start at 1.
+#[derive(Debug, Error)]
+pub enum LevelError {
+    #[error("{line}:{column}: {message}")]
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{line}:{column}: unknown tile '{symbol}'")]
+    UnknownTile {
+        line: usize,
+        column: usize,
+        symbol: char,
+    },
+    #[error("{line}:{column}: no tile uses id {id}")]
+    UnknownTileId { line: usize, column: usize, id: u8 },
+    #[error("{line}:{column}: row has {found} tiles, expected {expected}")]
+    RaggedRow {
+        line: usize,
+        column: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("{line}:{column}: map border is open at ({x}, {y})")]
+    OpenBorder {
+        line: usize,
+        column: usize,
+        x: u32,
+        y: u32,
+    },
+    #[error("no spawn point, set `spawn` under [level] or place a spawn tile")]
+    MissingSpawn,
+    #[error("{line}:{column}: spawn point is already set")]
+    DuplicateSpawn { line: usize, column: usize },
+    #[error("{line}:{column}: spawn point ({x}, {y}) is not in an empty cell")]
+    SpawnInWall {
+        line: usize,
+        column: usize,
+        x: f32,
+        y: f32,
+    },
+    #[error("level has no [grid] section")]
+    MissingGrid,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// A playable map loaded from a level file.
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: Option<String>,
+    pub map: TileMap,
+    /// Where the player starts, in tiles.
+    pub spawn: glm::Vec2,
+    /// Unit direction the player starts facing, north (0, -1) by default.
+    pub facing: glm::Vec2,
+    /// Atlas image path. Relative to the level file when loaded with
+    /// [`Level::load`].
+    pub atlas: Option<PathBuf>,
+    /// Region of the atlas image drawn for each tile id.
+    pub textures: Vec<(Tile, AtlasRegion)>,
+    /// Sprites placed in the level, their textures are regions of the atlas.
+    pub sprites: Vec<Sprite>,
+}
+
+impl Level {
+    /// Reads and parses the level at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let mut level = Self::parse(&std::fs::read_to_string(path)?)?;
+        if let (Some(atlas), Some(dir)) = (&level.atlas, path.parent()) {
+            level.atlas = Some(dir.join(atlas));
+        }
+        Ok(level)
+    }
+
+    pub fn parse(src: &str) -> Result<Self, LevelError> {
+        Parser::default().parse(src)
+    }
+
+    /// Camera standing at the spawn point, facing the level's direction.
+    pub fn camera(&self) -> Camera {
+        Camera::new(self.spawn, self.facing)
+    }
+
+    /// Loads the atlas image, with every texture of the level mapped.
+    /// `None` if the level has no atlas.
+    pub fn load_atlas(&self) -> anyhow::Result<Option<TextureAtlas>> {
+        let Some(path) = &self.atlas else {
+            return Ok(None);
+        };
+        let mut atlas = TextureAtlas::from_image(&image::open(path)?);
+        for (tile, region) in &self.textures {
+            atlas.set_region(*tile, *region)?;
+        }
+        Ok(Some(atlas))
+    }
+}
+
+impl FromStr for Level {
+    type Err = LevelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    None,
+    Level,
+    Tiles,
+    Textures,
+    Grid,
+}
+
+/// What a symbol in the grid stands for.
+#[derive(Debug, Clone, Copy)]
+struct TileDef {
+    wall: Tile,
+    floor: Option<Tile>,
+    ceiling: Option<Tile>,
+    spawn: bool,
+}
+
+/// A line of the file, for pointing errors at parts of it.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    no: usize,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    /// Column of `part`, which must be a slice of this line.
+    fn column(&self, part: &str) -> usize {
+        let offset = part.as_ptr() as usize - self.text.as_ptr() as usize;
+        self.text[..offset].chars().count() + 1
+    }
+
+    fn error(&self, part: &str, message: impl Display) -> LevelError {
+        LevelError::Syntax {
+            line: self.no,
+            column: self.column(part),
+            message: message.to_string(),
+        }
+    }
+
+    /// The line without its comment and surrounding whitespace.
+    fn content(&self) -> &'a str {
+        self.text.split(';').next().unwrap_or("").trim()
+    }
+
+    /// Splits a `key = value` line, both sides trimmed.
+    fn key_value(&self) -> Result<(&'a str, &'a str), LevelError> {
+        let text = self.content();
+        let (key, value) = text
+            .split_once('=')
+            .ok_or_else(|| self.error(text, "expected `key = value`"))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(self.error(text, "missing key before '='"));
+        }
+        Ok((key, value.trim()))
+    }
+
+    fn number<T: FromStr>(&self, word: &str) -> Result<T, LevelError> {
+        word.parse()
+            .map_err(|_| self.error(word, format!("expected a number, got `{}`", word)))
+    }
+
+    /// Parses every word of `value` as a number, there must be exactly `N`.
+    fn numbers<T: FromStr + Copy + Default, const N: usize>(
+        &self,
+        value: &str,
+    ) -> Result<[T; N], LevelError> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        if words.len() != N {
+            return Err(self.error(
+                value,
+                format!("expected {} numbers, got {}", N, words.len()),
+            ));
+        }
+        let mut out = [T::default(); N];
+        for (o, w) in out.iter_mut().zip(words) {
+            *o = self.number(w)?;
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Default)]
+struct Parser<'a> {
+    name: Option<String>,
+    atlas: Option<PathBuf>,
+    facing: Option<glm::Vec2>,
+    floor: Option<Tile>,
+    ceiling: Option<Tile>,
+    /// Spawn point and the line it was set on.
+    spawn: Option<(glm::Vec2, Line<'a>, &'a str)>,
+    tiles: HashMap<char, TileDef>,
+    textures: Vec<(Tile, AtlasRegion, Line<'a>, &'a str)>,
+    grid: Vec<Line<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self, src: &'a str) -> Result<Level, LevelError> {
+        let mut section = Section::None;
+        let mut has_grid = false;
+
+        for (i, text) in src.lines().enumerate() {
+            let line = Line {
+                no: i + 1,
+                text: text.trim_end_matches('\r'),
+            };
+
+            if section == Section::Grid {
+                self.grid.push(line);
+                continue;
+            }
+
+            let content = line.content();
+            if content.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = content.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .ok_or_else(|| line.error(content, "expected `]`"))?;
+                section = match name.trim() {
+                    "level" => Section::Level,
+                    "tiles" => Section::Tiles,
+                    "textures" => Section::Textures,
+                    "grid" => Section::Grid,
+                    other => {
+                        return Err(line.error(content, format!("unknown section [{}]", other)))
+                    }
+                };
+                has_grid |= section == Section::Grid;
+                continue;
+            }
+
+            match section {
+                Section::None => return Err(line.error(content, "expected a section like [level]")),
+                Section::Level => self.level_entry(line)?,
+                Section::Tiles => self.tile_entry(line)?,
+                Section::Textures => self.texture_entry(line)?,
+                Section::Grid => unreachable!(),
+            }
+        }
+
+        if !has_grid {
+            return Err(LevelError::MissingGrid);
+        }
+        self.build()
+    }
+
+    fn level_entry(&mut self, line: Line<'a>) -> Result<(), LevelError> {
+        let (key, value) = line.key_value()?;
+        match key {
+            "name" => self.name = Some(value.to_string()),
+            "atlas" => self.atlas = Some(PathBuf::from(value)),
+            "floor" => self.floor = Some(Tile(line.number(value)?)),
+            "ceiling" => self.ceiling = Some(Tile(line.number(value)?)),
+            "facing" => {
+                let dir = match compass(value) {
+                    Some(dir) => dir,
+                    None => {
+                        let [x, y] = line.numbers::<f32, 2>(value)?;
+                        glm::vec2(x, y)
+                    }
+                };
+                if glm::length2(&dir) == 0. {
+                    return Err(line.error(value, "facing direction can't be zero"));
+                }
+                self.facing = Some(glm::normalize(&dir));
+            }
+            "spawn" => {
+                let [x, y] = line.numbers::<f32, 2>(value)?;
+                self.set_spawn(glm::vec2(x, y), line, value)?;
+            }
+            _ => return Err(line.error(key, format!("unknown key `{}`", key))),
+        }
+        Ok(())
+    }
+
+    fn tile_entry(&mut self, line: Line<'a>) -> Result<(), LevelError> {
+        let (key, value) = line.key_value()?;
+        let mut chars = key.chars();
+        let symbol = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return Err(line.error(key, "tile symbol must be a single character")),
+        };
+
+        let mut words = value.split_whitespace();
+        let Some(first) = words.next() else {
+            return Err(line.error(value, "expected a tile id or `spawn`"));
+        };
+        let mut def = TileDef {
+            wall: Tile::EMPTY,
+            floor: None,
+            ceiling: None,
+            spawn: first == "spawn",
+        };
+        if !def.spawn {
+            def.wall = Tile(line.number(first)?);
+        }
+
+        while let Some(word) = words.next() {
+            let layer = match word {
+                "floor" => &mut def.floor,
+                "ceiling" => &mut def.ceiling,
+                _ => {
+                    return Err(line.error(
+                        word,
+                        format!("expected `floor` or `ceiling`, got `{}`", word),
+                    ))
+                }
+            };
+            let id = words
+                .next()
+                .ok_or_else(|| line.error(word, format!("missing tile id after `{}`", word)))?;
+            *layer = Some(Tile(line.number(id)?));
+        }
+
+        if self.tiles.insert(symbol, def).is_some() {
+            return Err(line.error(key, format!("tile '{}' is defined twice", symbol)));
+        }
+        Ok(())
+    }
+
+    fn texture_entry(&mut self, line: Line<'a>) -> Result<(), LevelError> {
+        let (key, value) = line.key_value()?;
+        let tile = Tile(line.number(key)?);
+        let [x, y, w, h] = line.numbers::<u32, 4>(value)?;
+        self.textures
+            .push((tile, AtlasRegion::new(x, y, w, h), line, key));
+        Ok(())
+    }
+
+    fn set_spawn(
+        &mut self,
+        pos: glm::Vec2,
+        line: Line<'a>,
+        part: &'a str,
+    ) -> Result<(), LevelError> {
+        if self.spawn.is_some() {
+            return Err(LevelError::DuplicateSpawn {
+                line: line.no,
+                column: line.column(part),
+            });
+        }
+        self.spawn = Some((pos, line, part));
+        Ok(())
+    }
+
+    fn build(mut self) -> Result<Level, LevelError> {
+        // blank lines at the end of the file aren't rows
+        while self.grid.last().is_some_and(|l| l.text.trim().is_empty()) {
+            self.grid.pop();
+        }
+        let grid = std::mem::take(&mut self.grid);
+        let Some(first) = grid.first() else {
+            return Err(LevelError::MissingGrid);
+        };
+
+        let width = first.text.chars().count();
+        let height = grid.len();
+        let mut map = TileMap::new(width as u32, height as u32);
+        map.fill_floor(self.floor.unwrap_or(Tile::EMPTY));
+        map.fill_ceiling(self.ceiling.unwrap_or(Tile::EMPTY));
+
+        for (y, line) in grid.iter().enumerate() {
+            let found = line.text.chars().count();
+            if found != width {
+                return Err(LevelError::RaggedRow {
+                    line: line.no,
+                    column: found.min(width) + 1,
+                    expected: width,
+                    found,
+                });
+            }
+
+            for (x, (offset, symbol)) in line.text.char_indices().enumerate() {
+                let def = *self.tiles.get(&symbol).ok_or(LevelError::UnknownTile {
+                    line: line.no,
+                    column: x + 1,
+                    symbol,
+                })?;
+                let (x, y) = (x as i32, y as i32);
+                map.set(x, y, def.wall);
+                if let Some(floor) = def.floor {
+                    map.set_floor(x, y, floor);
+                }
+                if let Some(ceiling) = def.ceiling {
+                    map.set_ceiling(x, y, ceiling);
+                }
+                if def.spawn {
+                    let pos = glm::vec2(x as f32 + 0.5, y as f32 + 0.5);
+                    self.set_spawn(pos, *line, &line.text[offset..])?;
+                }
+            }
+        }
+
+        for (y, line) in grid.iter().enumerate() {
+            for x in 0..width {
+                let border = y == 0 || y == height - 1 || x == 0 || x == width - 1;
+                if border && !map.is_solid(x as i32, y as i32) {
+                    return Err(LevelError::OpenBorder {
+                        line: line.no,
+                        column: x + 1,
+                        x: x as u32,
+                        y: y as u32,
+                    });
+                }
+            }
+        }
+
+        let (spawn, line, part) = self.spawn.ok_or(LevelError::MissingSpawn)?;
+        if map.is_solid(spawn.x.floor() as i32, spawn.y.floor() as i32) {
+            return Err(LevelError::SpawnInWall {
+                line: line.no,
+                column: line.column(part),
+                x: spawn.x,
+                y: spawn.y,
+            });
+        }
+
+        let mut textures = Vec::with_capacity(self.textures.len());
+        for (tile, region, line, key) in self.textures {
+            let used = self
+                .tiles
+                .values()
+                .any(|d| d.wall == tile || d.floor == Some(tile) || d.ceiling == Some(tile))
+                || self.floor == Some(tile)
+                || self.ceiling == Some(tile);
+            if !used {
+                return Err(LevelError::UnknownTileId {
+                    line: line.no,
+                    column: line.column(key),
+                    id: tile.id(),
+                });
+            }
+            textures.push((tile, region));
+        }
+
+        Ok(Level {
+            name: self.name,
+            map,
+            spawn,
+            facing: self.facing.unwrap_or(glm::vec2(0., -1.)),
+            atlas: self.atlas,
+            textures,
+            sprites: Vec::new(),
+        })
+    }
+}
@@ -1,6 +1,18 @@
+pub mod app;
+pub mod atlas;
+pub mod camera;
+pub mod door;
 pub mod geom;
 pub mod gfx;
+pub mod gpu_raycast;
+pub mod input;
+pub mod level;
+pub mod light;
+pub mod map;
 pub mod math;
+pub mod ray;
 pub mod raycast;
 pub mod render;
 pub mod shader;
+pub mod sprite;
+pub mod tiled;
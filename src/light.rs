@@ -0,0 +1,114 @@
+use image::Rgb;
+
+/// How fog thickens with distance.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Fog {
+    #[default]
+    None,
+    /// Clear up to `start` tiles away, fully fogged from `end` on.
+    Linear { start: f32, end: f32 },
+    /// `1 - e^(-density * dist)` of the color is fog.
+    Exponential { density: f32 },
+}
+
+/// Distance fog and light levels for the software raycaster.
+///
+/// Every surface is lit by the light level of its cell (see
+/// [`crate::map::TileMap::set_light`]), raised to at least `ambient`, then
+/// blended towards `fog_color` with distance. The default changes nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lighting {
+    pub fog: Fog,
+    pub fog_color: Rgb<u8>,
+    /// Brightness in [0, 1] that even cells with no light get.
+    pub ambient: f32,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            fog: Fog::None,
+            fog_color: Rgb([0, 0, 0]),
+            ambient: 0.,
+        }
+    }
+}
+
+impl Lighting {
+    pub fn with_fog(mut self, fog: Fog, color: Rgb<u8>) -> Self {
+        self.fog = fog;
+        self.fog_color = color;
+        self
+    }
+
+    pub fn with_ambient(mut self, ambient: f32) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    /// How much of a surface `dist` tiles away is covered by fog, from 0
+    /// to 1.
+    pub fn fog_amount(&self, dist: f32) -> f32 {
+        match self.fog {
+            Fog::None => 0.,
+            Fog::Linear { start, end } if end <= start => (dist >= end) as i32 as f32,
+            Fog::Linear { start, end } => ((dist - start) / (end - start)).clamp(0., 1.),
+            Fog::Exponential { density } => 1. - (-density * dist.max(0.)).exp(),
+        }
+    }
+
+    /// Brightness of a cell with light level `light`.
+    #[inline]
+    pub fn brightness(&self, light: f32) -> f32 {
+        let ambient = self.ambient.clamp(0., 1.);
+        ambient + (1. - ambient) * light.clamp(0., 1.)
+    }
+
+    /// Shade for a surface `dist` tiles away in a cell with light level
+    /// `light`.
+    #[inline]
+    pub fn shade(&self, light: f32, dist: f32) -> Shade {
+        self.shade_fogged(light, self.fog_amount(dist))
+    }
+
+    /// [`Lighting::shade`] with the fog amount already worked out, for when
+    /// many surfaces are the same distance away.
+    #[inline]
+    pub fn shade_fogged(&self, light: f32, fog: f32) -> Shade {
+        Shade {
+            brightness: self.brightness(light),
+            fog,
+            fog_color: self.fog_color,
+        }
+    }
+}
+
+/// Lighting worked out for one surface, see [`Lighting::shade`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shade {
+    brightness: f32,
+    fog: f32,
+    fog_color: Rgb<u8>,
+}
+
+impl Shade {
+    /// Leaves colors as they are.
+    pub const NONE: Shade = Shade {
+        brightness: 1.,
+        fog: 0.,
+        fog_color: Rgb([0, 0, 0]),
+    };
+
+    #[inline]
+    pub fn apply(&self, color: Rgb<u8>) -> Rgb<u8> {
+        if self.brightness >= 1. && self.fog <= 0. {
+            return color;
+        }
+        let lit = self.brightness * (1. - self.fog);
+        let mut out = [0; 3];
+        for ((o, c), f) in out.iter_mut().zip(color.0).zip(self.fog_color.0) {
+            *o = (c as f32 * lit + f as f32 * self.fog).round().min(255.) as u8;
+        }
+        Rgb(out)
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use nalgebra_glm as glm;
+
+use crate::door::{Door, ThinWall};
+
+/// A single cell of a [`TileMap`].
+///
+/// Id `0` is open floor, every other id is a solid wall whose look is picked
+/// by the renderer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile(pub u8);
+
+impl Tile {
+    pub const EMPTY: Self = Self(0);
+
+    #[inline]
+    pub const fn id(self) -> u8 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub const fn is_wall(self) -> bool {
+        !self.is_empty()
+    }
+}
+
+impl From<u8> for Tile {
+    fn from(value: u8) -> Self {
+        Self(value)
+    }
+}
+
+/// Grid of [`Tile`]s stored row-major, `x` grows to the right and `y` grows
+/// down (row 0 is the first row of the map).
+///
+/// Besides the wall layer every cell has a floor and a ceiling tile, these
+/// only pick the texture drawn under and over the cell. An empty floor or
+/// ceiling tile is not drawn.
+///
+/// Every cell also has a light level, see [`crate::light::Lighting`].
+///
+/// Wall cells fill the whole cell unless they are made into a [`ThinWall`]
+/// or a [`Door`], which only block a line through the cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMap {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    floor: Vec<Tile>,
+    ceiling: Vec<Tile>,
+    /// Light level per cell, in [0, 1].
+    light: Vec<f32>,
+    /// Wall cells that don't fill their cell, by cell index.
+    shapes: HashMap<usize, Shape>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Thin(ThinWall),
+    Door(Door),
+}
+
+impl TileMap {
+    /// Creates a `width` x `height` map with every tile empty. Panics if
+    /// there are more cells than can be addressed, see
+    /// [`TileMap::cell_count`].
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = Self::cell_count(width, height)
+            .unwrap_or_else(|| panic!("TileMap of size {width}x{height} is too large"));
+        Self {
+            width,
+            height,
+            tiles: vec![Tile::EMPTY; len],
+            floor: vec![Tile::EMPTY; len],
+            ceiling: vec![Tile::EMPTY; len],
+            light: vec![1.; len],
+            shapes: HashMap::new(),
+        }
+    }
+
+    pub fn from_tiles(width: u32, height: u32, tiles: Vec<Tile>) -> anyhow::Result<Self> {
+        let Some(expected) = Self::cell_count(width, height) else {
+            bail!("TileMap of size {}x{} is too large", width, height);
+        };
+        if tiles.len() != expected {
+            bail!(
+                "TileMap of size {}x{} needs {} tiles, got {}",
+                width,
+                height,
+                expected,
+                tiles.len()
+            );
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles,
+            floor: vec![Tile::EMPTY; expected],
+            ceiling: vec![Tile::EMPTY; expected],
+            light: vec![1.; expected],
+            shapes: HashMap::new(),
+        })
+    }
+
+    /// Builds a map from rows of tile ids, `rows[y][x]`. Every row must have
+    /// the same length.
+    pub fn from_rows<R: AsRef<[u8]>>(rows: &[R]) -> anyhow::Result<Self> {
+        let width = rows.first().map(|r| r.as_ref().len()).unwrap_or(0);
+        let (Ok(w), Ok(h)) = (u32::try_from(width), u32::try_from(rows.len())) else {
+            bail!("TileMap of size {}x{} is too large", width, rows.len());
+        };
+
+        let mut tiles = Vec::with_capacity(width * rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            let row = row.as_ref();
+            if row.len() != width {
+                bail!(
+                    "TileMap row {} has {} tiles, expected {}",
+                    y,
+                    row.len(),
+                    width
+                );
+            }
+            tiles.extend(row.iter().copied().map(Tile));
+        }
+        Self::from_tiles(w, h, tiles)
+    }
+
+    /// Number of cells in a `width` x `height` map, `None` if that doesn't
+    /// fit in a `usize`.
+    #[inline]
+    pub fn cell_count(width: u32, height: u32) -> Option<usize> {
+        (width as usize).checked_mul(height as usize)
+    }
+
+    #[inline]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
+
+    #[inline]
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if self.in_bounds(x, y) {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Tile at (x, y), or `None` if the cell is outside of the map.
+    #[inline]
+    pub fn get(&self, x: i32, y: i32) -> Option<Tile> {
+        self.index(x, y).map(|i| self.tiles[i])
+    }
+
+    /// Sets the tile at (x, y), turning a thin wall or door back into a
+    /// plain cell. Returns false if the cell is outside of the map.
+    pub fn set(&mut self, x: i32, y: i32, tile: Tile) -> bool {
+        match self.index(x, y) {
+            Some(i) => {
+                self.tiles[i] = tile;
+                self.shapes.remove(&i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes (x, y) a thin wall drawn with `tile`, which must be a wall.
+    /// Returns false if the cell is outside of the map.
+    pub fn set_thin_wall(&mut self, x: i32, y: i32, tile: Tile, wall: ThinWall) -> bool {
+        self.set_shape(x, y, tile, Shape::Thin(wall))
+    }
+
+    /// Puts `door` in (x, y), drawn with `tile`, which must be a wall.
+    /// Returns false if the cell is outside of the map.
+    pub fn set_door(&mut self, x: i32, y: i32, tile: Tile, door: Door) -> bool {
+        self.set_shape(x, y, tile, Shape::Door(door))
+    }
+
+    fn set_shape(&mut self, x: i32, y: i32, tile: Tile, shape: Shape) -> bool {
+        debug_assert!(tile.is_wall(), "thin walls and doors need a wall tile");
+        match self.index(x, y) {
+            Some(i) => {
+                self.tiles[i] = tile;
+                self.shapes.insert(i, shape);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Thin wall at (x, y), doors included.
+    pub fn thin_wall(&self, x: i32, y: i32) -> Option<ThinWall> {
+        match self.index(x, y).and_then(|i| self.shapes.get(&i))? {
+            Shape::Thin(wall) => Some(*wall),
+            Shape::Door(door) => Some(door.wall),
+        }
+    }
+
+    pub fn door(&self, x: i32, y: i32) -> Option<&Door> {
+        match self.index(x, y).and_then(|i| self.shapes.get(&i))? {
+            Shape::Door(door) => Some(door),
+            Shape::Thin(_) => None,
+        }
+    }
+
+    pub fn door_mut(&mut self, x: i32, y: i32) -> Option<&mut Door> {
+        let i = self.index(x, y)?;
+        match self.shapes.get_mut(&i)? {
+            Shape::Door(door) => Some(door),
+            Shape::Thin(_) => None,
+        }
+    }
+
+    /// Starts opening the door at (x, y). Returns false if there is no door.
+    pub fn open_door(&mut self, x: i32, y: i32) -> bool {
+        self.door_mut(x, y).map(Door::open).is_some()
+    }
+
+    /// Starts closing the door at (x, y). Returns false if there is no door.
+    pub fn close_door(&mut self, x: i32, y: i32) -> bool {
+        self.door_mut(x, y).map(Door::close).is_some()
+    }
+
+    /// Opens or closes the door at (x, y). Returns false if there is no door.
+    pub fn toggle_door(&mut self, x: i32, y: i32) -> bool {
+        self.door_mut(x, y).map(Door::toggle).is_some()
+    }
+
+    /// Cells with doors and the doors in them.
+    pub fn doors(&self) -> impl Iterator<Item = (glm::IVec2, &Door)> + '_ {
+        self.shapes.iter().filter_map(|(i, shape)| match shape {
+            Shape::Door(door) => Some((self.cell_of(*i), door)),
+            Shape::Thin(_) => None,
+        })
+    }
+
+    /// Slides every opening or closing door by `dt` seconds.
+    pub fn update_doors(&mut self, dt: f32) {
+        for shape in self.shapes.values_mut() {
+            if let Shape::Door(door) = shape {
+                door.update(dt);
+            }
+        }
+    }
+
+    #[inline]
+    fn cell_of(&self, i: usize) -> glm::IVec2 {
+        let w = self.width as usize;
+        glm::vec2((i % w) as i32, (i / w) as i32)
+    }
+
+    /// Floor tile under (x, y), or `None` if the cell is outside of the map.
+    #[inline]
+    pub fn floor(&self, x: i32, y: i32) -> Option<Tile> {
+        self.index(x, y).map(|i| self.floor[i])
+    }
+
+    /// Ceiling tile over (x, y), or `None` if the cell is outside of the map.
+    #[inline]
+    pub fn ceiling(&self, x: i32, y: i32) -> Option<Tile> {
+        self.index(x, y).map(|i| self.ceiling[i])
+    }
+
+    pub fn set_floor(&mut self, x: i32, y: i32, tile: Tile) -> bool {
+        match self.index(x, y) {
+            Some(i) => {
+                self.floor[i] = tile;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_ceiling(&mut self, x: i32, y: i32, tile: Tile) -> bool {
+        match self.index(x, y) {
+            Some(i) => {
+                self.ceiling[i] = tile;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the floor of every cell to `tile`.
+    pub fn fill_floor(&mut self, tile: Tile) {
+        self.floor.fill(tile);
+    }
+
+    /// Sets the ceiling of every cell to `tile`.
+    pub fn fill_ceiling(&mut self, tile: Tile) {
+        self.ceiling.fill(tile);
+    }
+
+    /// Light level of (x, y) from 0 (dark) to 1 (fully lit), or `None` if
+    /// the cell is outside of the map. Cells start fully lit.
+    #[inline]
+    pub fn light(&self, x: i32, y: i32) -> Option<f32> {
+        self.index(x, y).map(|i| self.light[i])
+    }
+
+    pub fn set_light(&mut self, x: i32, y: i32, level: f32) -> bool {
+        match self.index(x, y) {
+            Some(i) => {
+                self.light[i] = level.clamp(0., 1.);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the light level of every cell to `level`.
+    pub fn fill_light(&mut self, level: f32) {
+        self.light.fill(level.clamp(0., 1.));
+    }
+
+    /// Whether all of (x, y) blocks movement. Cells outside of the map are
+    /// solid, thin walls and doors are not, see [`TileMap::collider`].
+    #[inline]
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        match self.index(x, y) {
+            Some(i) => self.tiles[i].is_wall() && !self.shapes.contains_key(&i),
+            None => true,
+        }
+    }
+
+    /// Box (min, max) of the part of (x, y) that blocks movement, if any. A
+    /// thin wall's box has no thickness and an open door has none.
+    pub fn collider(&self, x: i32, y: i32) -> Option<(glm::Vec2, glm::Vec2)> {
+        if self.is_solid(x, y) {
+            let min = glm::vec2(x as f32, y as f32);
+            return Some((min, min + glm::vec2(1., 1.)));
+        }
+        match self.index(x, y).and_then(|i| self.shapes.get(&i))? {
+            Shape::Thin(wall) => Some(wall.segment(x, y, 0., 1.)),
+            Shape::Door(door) if door.is_open() => None,
+            Shape::Door(door) => Some(door.wall.segment(x, y, door.amount(), 1.)),
+        }
+    }
+
+    #[inline]
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+}
@@ -1,13 +1,11 @@
-use std::ops::Mul;
-
 use nalgebra_glm as glm;
 
-pub const PI: f32 = 3.1415926535897932384626433832795;
-pub const HALF_PI: f32 = 1.5707963267948966192313216916398;
-pub const TWO_PI: f32 = 6.283185307179586476925286766559;
+pub const PI: f32 = std::f32::consts::PI;
+pub const HALF_PI: f32 = std::f32::consts::FRAC_PI_2;
+pub const TWO_PI: f32 = std::f32::consts::TAU;
 pub const DEG_TO_RAD: f32 = 0.017453292519943295769236907684886;
 pub const RAD_TO_DEG: f32 = 57.295779513082320876798154814105;
-pub const EULER: f32 = 2.718281828459045235360287471352;
+pub const EULER: f32 = std::f32::consts::E;
 
 #[inline]
 pub fn radians(degrees: f32) -> f32 {
@@ -52,7 +50,7 @@ impl TransformBuilder {
         self
     }
 
-    pub fn build(mut self) -> Transform {
+    pub fn build(self) -> Transform {
         Transform {
             position: self.position.unwrap_or_default(),
             scale: self.scale.unwrap_or(glm::vec2(1., 1.)),
@@ -144,17 +142,7 @@ impl Transform {
 
     pub fn build_model(&mut self) -> &glm::Mat4 {
         if self.needs_update {
-            let pos = glm::vec2_to_vec3(&self.position);
-            let origin_offset = glm::vec2_to_vec3(&self.origin_offset);
-            let scale = glm::vec2_to_vec3(&self.scale);
-
-            let model = glm::translate(&glm::identity(), &pos);
-            let model = glm::translate(&model, &origin_offset);
-            let model = glm::rotate(&model, radians(self.rotation), &glm::vec3(0., 0., 1.));
-            let model = glm::translate(&model, &-origin_offset);
-            let model = glm::scale(&model, &scale);
-
-            self.model = model;
+            self.model = self.calc_model();
             self.needs_update = false;
 
             &self.model
@@ -163,6 +151,28 @@ impl Transform {
         }
     }
 
+    /// Same matrix as [`Transform::build_model`], for when the transform
+    /// can't be borrowed mutably to cache it.
+    pub fn model(&self) -> glm::Mat4 {
+        if self.needs_update {
+            self.calc_model()
+        } else {
+            self.model
+        }
+    }
+
+    fn calc_model(&self) -> glm::Mat4 {
+        let pos = glm::vec2_to_vec3(&self.position);
+        let origin_offset = glm::vec2_to_vec3(&self.origin_offset);
+        let scale = glm::vec2_to_vec3(&self.scale);
+
+        let model = glm::translate(&glm::identity(), &pos);
+        let model = glm::translate(&model, &origin_offset);
+        let model = glm::rotate(&model, radians(self.rotation), &glm::vec3(0., 0., 1.));
+        let model = glm::translate(&model, &-origin_offset);
+        glm::scale(&model, &scale)
+    }
+
     pub const fn cached_model(&self) -> &glm::Mat4 {
         &self.model
     }
@@ -0,0 +1,236 @@
+use nalgebra_glm as glm;
+
+use crate::{
+    door::Orientation,
+    map::{Tile, TileMap},
+};
+
+/// Which kind of cell face a ray crossed. The map's y axis grows down, so
+/// north is towards y = 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// East or west face of a cell, one perpendicular to the x axis.
+    EastWest,
+    /// North or south face of a cell, one perpendicular to the y axis.
+    NorthSouth,
+}
+
+/// A cell visited by [`RayCells`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayStep {
+    pub cell: glm::IVec2,
+    pub tile: Tile,
+    /// Face the ray entered the cell through, `None` for the cell the ray
+    /// starts in.
+    pub side: Option<Side>,
+    /// Distance along the ray where it entered the cell, in lengths of the
+    /// ray's direction. See [`RayHit::perp_dist`].
+    pub perp_dist: f32,
+}
+
+/// Where a ray cast with [`cast_ray`] hit a wall.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub cell: glm::IVec2,
+    pub tile: Tile,
+    pub side: Side,
+    /// Distance to the hit in lengths of the ray's direction. For a ray of
+    /// `cam.dir() + cam.plane() * x` this is the distance from the camera
+    /// plane, which keeps walls from looking bent. Same as `dist` for unit
+    /// directions.
+    pub perp_dist: f32,
+    /// Straight line distance from the origin to `point`.
+    pub dist: f32,
+    /// Exact point hit, on the face of `cell`.
+    pub point: glm::Vec2,
+    /// Horizontal texture coordinate of `point` in [0, 1]. Seen from the
+    /// ray's origin it grows from right to left, the same on every face.
+    pub wall_u: f32,
+}
+
+impl RayHit {
+    /// Texture coordinates of the hit `z` wall heights above the floor,
+    /// v grows down from 0 at the top of the wall.
+    #[inline]
+    pub fn wall_uv(&self, z: f32) -> glm::Vec2 {
+        glm::vec2(self.wall_u, 1. - z)
+    }
+
+    /// Unit normal of the face that was hit, pointing back towards the ray.
+    pub fn normal(&self, dir: glm::Vec2) -> glm::Vec2 {
+        match self.side {
+            Side::EastWest => glm::vec2(-dir.x.signum(), 0.),
+            Side::NorthSouth => glm::vec2(0., -dir.y.signum()),
+        }
+    }
+}
+
+/// Casts a ray from `origin` along `dir` and returns the first wall it hits
+/// no more than `max_dist` away. The cell the ray starts in is never hit
+/// unless it's a thin wall or door in front of the origin, so rays starting
+/// inside of a wall see out of it. `None` if the ray leaves the map or goes
+/// past `max_dist` first.
+///
+/// Doors are hit on the part that hasn't slid open yet, the texture moves
+/// along with the door.
+pub fn cast_ray(map: &TileMap, origin: glm::Vec2, dir: glm::Vec2, max_dist: f32) -> Option<RayHit> {
+    let len = glm::length(&dir);
+    let (side, perp_dist, along, step) = RayCells::new(map, origin, dir, max_dist)
+        .filter(|s| s.tile.is_wall())
+        .find_map(|step| {
+            let (x, y) = (step.cell.x, step.cell.y);
+            let Some(wall) = map.thin_wall(x, y) else {
+                // full walls are hit where the ray enters them
+                let side = step.side?;
+                let point = origin + dir * step.perp_dist;
+                let along = match side {
+                    Side::EastWest => point.y,
+                    Side::NorthSouth => point.x,
+                };
+                return Some((side, step.perp_dist, along - along.floor(), step));
+            };
+
+            let (side, t, along) = match wall.orientation {
+                Orientation::Vertical if dir.x != 0. => {
+                    let t = (x as f32 + wall.offset - origin.x) / dir.x;
+                    (Side::EastWest, t, origin.y + dir.y * t - y as f32)
+                }
+                Orientation::Horizontal if dir.y != 0. => {
+                    let t = (y as f32 + wall.offset - origin.y) / dir.y;
+                    (Side::NorthSouth, t, origin.x + dir.x * t - x as f32)
+                }
+                _ => return None,
+            };
+            if t <= 0. || t < step.perp_dist || t * len > max_dist || !(0. ..=1.).contains(&along) {
+                return None;
+            }
+            match map.door(x, y) {
+                Some(door) if along < door.amount() => None,
+                Some(door) => Some((side, t, along - door.amount(), step)),
+                None => Some((side, t, along, step)),
+            }
+        })?;
+
+    // flipped on the faces where it would otherwise go the other way
+    let flip = match side {
+        Side::EastWest => dir.x > 0.,
+        Side::NorthSouth => dir.y < 0.,
+    };
+
+    Some(RayHit {
+        cell: step.cell,
+        tile: step.tile,
+        side,
+        perp_dist,
+        dist: perp_dist * len,
+        point: origin + dir * perp_dist,
+        wall_u: if flip { 1. - along } else { along },
+    })
+}
+
+/// Every cell a ray crosses, in order, starting with the one it starts in.
+/// Stops when the ray leaves the map or goes further than `max_dist`, walls
+/// don't stop it.
+pub fn ray_cells(map: &TileMap, origin: glm::Vec2, dir: glm::Vec2, max_dist: f32) -> RayCells<'_> {
+    RayCells::new(map, origin, dir, max_dist)
+}
+
+/// Grid traversal (DDA) behind [`cast_ray`], see [`ray_cells`].
+#[derive(Debug, Clone)]
+pub struct RayCells<'a> {
+    map: &'a TileMap,
+    cell: glm::IVec2,
+    /// Which way the ray steps along each axis, -1 or 1.
+    step: glm::IVec2,
+    /// Distance along the ray to the next x and y grid line.
+    side_dist: glm::Vec2,
+    /// Distance along the ray between two x or two y grid lines.
+    delta_dist: glm::Vec2,
+    /// `max_dist` in lengths of the ray's direction.
+    max_perp: f32,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> RayCells<'a> {
+    pub fn new(map: &'a TileMap, origin: glm::Vec2, dir: glm::Vec2, max_dist: f32) -> Self {
+        let cell = glm::vec2(origin.x.floor() as i32, origin.y.floor() as i32);
+
+        let delta = |d: f32| {
+            if d == 0. {
+                f32::INFINITY
+            } else {
+                (1. / d).abs()
+            }
+        };
+        let delta_dist = glm::vec2(delta(dir.x), delta(dir.y));
+
+        let mut step = glm::vec2(1, 1);
+        let mut side_dist = glm::vec2(f32::INFINITY, f32::INFINITY);
+        if dir.x < 0. {
+            step.x = -1;
+            side_dist.x = (origin.x - cell.x as f32) * delta_dist.x;
+        } else if dir.x > 0. {
+            side_dist.x = (cell.x as f32 + 1. - origin.x) * delta_dist.x;
+        }
+        if dir.y < 0. {
+            step.y = -1;
+            side_dist.y = (origin.y - cell.y as f32) * delta_dist.y;
+        } else if dir.y > 0. {
+            side_dist.y = (cell.y as f32 + 1. - origin.y) * delta_dist.y;
+        }
+
+        let len = glm::length(&dir);
+        Self {
+            map,
+            cell,
+            step,
+            side_dist,
+            delta_dist,
+            max_perp: if len > 0. { max_dist / len } else { 0. },
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for RayCells<'_> {
+    type Item = RayStep;
+
+    fn next(&mut self) -> Option<RayStep> {
+        if self.done {
+            return None;
+        }
+
+        let (side, perp_dist) = if !self.started {
+            self.started = true;
+            (None, 0.)
+        } else if self.side_dist.x < self.side_dist.y {
+            let d = self.side_dist.x;
+            self.side_dist.x += self.delta_dist.x;
+            self.cell.x += self.step.x;
+            (Some(Side::EastWest), d)
+        } else {
+            let d = self.side_dist.y;
+            self.side_dist.y += self.delta_dist.y;
+            self.cell.y += self.step.y;
+            (Some(Side::NorthSouth), d)
+        };
+
+        // a zero direction never reaches a grid line
+        let tile = match self.map.get(self.cell.x, self.cell.y) {
+            Some(tile) if perp_dist.is_finite() && perp_dist <= self.max_perp => tile,
+            _ => {
+                self.done = true;
+                return None;
+            }
+        };
+
+        Some(RayStep {
+            cell: self.cell,
+            tile,
+            side,
+            perp_dist,
+        })
+    }
+}
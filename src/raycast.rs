@@ -1,24 +1,37 @@
-use std::ops::{Add, Mul};
-
 use anyhow::anyhow;
-use gfx::SDLTextureBuf;
-use image::Pixel;
+use gfx::{
+    ColumnBuffer, FrameBuf, Layout, PixelBuffer, SDLTextureBuf, Scaling, TextureHandle,
+    WgpuTextureBuf,
+};
 use nalgebra_glm as glm;
 use sdl2::{
-    event::Event,
+    event::{Event, WindowEvent},
     keyboard::Keycode,
-    pixels::{PixelFormat, PixelFormatEnum},
-    rect::{Point, Rect},
-    render::{Canvas, Texture, TextureCreator},
+    render::{Canvas, TextureCreator},
     video::{Window, WindowContext},
-    EventPump, Sdl,
+    Sdl,
 };
 
-use crate::gfx;
+use crate::{
+    app::{App, Control, Game, Present},
+    atlas::{AtlasRegion, TextureAtlas},
+    camera::Camera,
+    door::{Door, Orientation, ThinWall},
+    geom::{self, Quad},
+    gfx,
+    input::{InputState, MouseLook},
+    light::{Fog, Lighting, Shade},
+    map::{Tile, TileMap},
+    math::TransformBuilder,
+    ray::{cast_ray, Side},
+    render::{Backdrop, QuadRenderer},
+    sprite::Sprite,
+};
 
 const MAP_W: usize = 24;
 const MAP_H: usize = 24;
 
+/// Demo level, indexed `WORLD_MAP[y][x]`. Load it with [`TileMap::from_rows`].
 pub const WORLD_MAP: [[u8; MAP_W]; MAP_H] = [
     [
         1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
@@ -95,14 +108,106 @@ pub const WORLD_MAP: [[u8; MAP_W]; MAP_H] = [
 ];
 
 struct SDLContext {
-    ctx: Sdl,
+    _ctx: Sdl,
     canvas: Canvas<Window>,
-    event_pump: EventPump,
+}
+
+/// What [`RaycastRenderer`] and [`WgpuRaycastRenderer`] share: the raycaster
+/// and how it's threaded and scaled, drawing into the [`FrameBuf`] of
+/// whichever texture they present.
+#[derive(Debug)]
+struct RaycastCore {
+    threads: usize,
+    scaling: Scaling,
+    raycaster: Raycaster,
+}
+
+impl RaycastCore {
+    /// Renders on one thread per core, switching `frame` to
+    /// [`Layout::ColumnMajor`] if there's more than one.
+    fn new(frame: &mut FrameBuf) -> Self {
+        let mut s = Self {
+            threads: 1,
+            scaling: Scaling::Native,
+            raycaster: Raycaster::new(),
+        };
+        s.set_threads(
+            frame,
+            std::thread::available_parallelism().map_or(1, |n| n.get()),
+        );
+        s
+    }
+
+    fn set_threads(&mut self, frame: &mut FrameBuf, threads: usize) {
+        self.threads = threads.max(1);
+        if self.threads > 1 {
+            frame.set_layout(Layout::ColumnMajor);
+        }
+    }
+
+    fn set_layout(&mut self, frame: &mut FrameBuf, layout: Layout) {
+        if layout == Layout::RowMajor {
+            self.threads = 1;
+        }
+        frame.set_layout(layout);
+    }
+
+    /// Resolution to reallocate `frame` at for a `window` sized window.
+    /// `None` if it stays the same or the window has no area, as when it's
+    /// minimized.
+    fn resize(&self, frame: &FrameBuf, window: (u32, u32)) -> Option<(u32, u32)> {
+        let (w, h) = self.scaling.resolution(window);
+        if w == 0 || h == 0 || (w, h) == (frame.width(), frame.height()) {
+            return None;
+        }
+        Some((w, h))
+    }
+
+    fn draw_map(
+        &mut self,
+        frame: &mut FrameBuf,
+        map: &TileMap,
+        cam: &Camera,
+    ) -> anyhow::Result<()> {
+        match frame.columns_mut() {
+            Some(columns) => self
+                .raycaster
+                .render_threaded(columns, map, cam, self.threads),
+            None => self.raycaster.render(frame, map, cam),
+        }
+    }
+
+    fn clear(&self, frame: &mut FrameBuf, cam: &Camera) {
+        let horizon = cam.horizon(frame.height());
+        frame.clear_black_horizon(horizon);
+    }
+
+    fn draw_sprites(
+        &mut self,
+        frame: &mut FrameBuf,
+        map: &TileMap,
+        sprites: &[Sprite],
+        cam: &Camera,
+    ) {
+        self.raycaster.render_sprites(frame, map, sprites, cam);
+    }
+}
+
+/// Whether `event` may have changed the size of what's drawn to.
+fn resized(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Window {
+            win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..),
+            ..
+        }
+    )
 }
 
 pub struct RaycastRenderer {
     sdl: SDLContext,
     target: SDLTextureBuf,
+    core: RaycastCore,
     texture_creator: TextureCreator<WindowContext>,
 }
 
@@ -117,361 +222,941 @@ impl RaycastRenderer {
 
         let texture_creator = canvas.texture_creator();
 
-        let event_pump = ctx.event_pump().map_err(|e| anyhow!(e))?;
-
-        let sdl = SDLContext {
-            ctx,
-            canvas,
-            event_pump,
-        };
+        let sdl = SDLContext { _ctx: ctx, canvas };
 
-        let target = SDLTextureBuf::new(&texture_creator, width, height)?;
+        let mut target = SDLTextureBuf::new(&texture_creator, width, height)?;
+        let core = RaycastCore::new(target.frame_mut());
 
-        let s = Self {
+        Ok(Self {
             sdl,
             texture_creator,
             target,
+            core,
+        })
+    }
+
+    /// Number of threads the map is rendered on, one per core by default.
+    /// More than one needs the target in [`Layout::ColumnMajor`], which it's
+    /// switched to.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.core.set_threads(self.target.frame_mut(), threads);
+    }
+
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.core.threads
+    }
+
+    /// Memory layout of the frame being drawn. [`Layout::RowMajor`] also
+    /// goes back to rendering on one thread.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.core.set_layout(self.target.frame_mut(), layout);
+    }
+
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.target.layout()
+    }
+
+    /// How frames are fitted to the window, reallocating the render target
+    /// if that changes the resolution frames are rendered at.
+    pub fn set_scaling(&mut self, scaling: Scaling) -> anyhow::Result<()> {
+        self.core.scaling = scaling;
+        let window = self.sdl.canvas.output_size().map_err(|e| anyhow!(e))?;
+        self.resize(window)
+    }
+
+    #[inline]
+    pub fn scaling(&self) -> Scaling {
+        self.core.scaling
+    }
+
+    /// Size frames are rendered at.
+    #[inline]
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.target.width(), self.target.height())
+    }
+
+    /// Reallocates the render target for a `window` sized window, keeping
+    /// its format and layout, see [`RaycastCore::resize`].
+    fn resize(&mut self, window: (u32, u32)) -> anyhow::Result<()> {
+        let Some((w, h)) = self.core.resize(self.target.frame(), window) else {
+            return Ok(());
         };
-        Ok(s)
+        let mut target =
+            SDLTextureBuf::with_format(&self.texture_creator, w, h, self.target.format())?;
+        target.set_layout(self.target.layout());
+        self.target = target;
+        Ok(())
     }
 
-    pub fn draw_map(
-        &mut self,
-        map: &[u8],
-        cam_pos: glm::Vec2,
-        face_dir: glm::Vec2,
-    ) -> anyhow::Result<()> {
-        todo!()
+    /// Raycasts `map` as seen from `cam` into the render target.
+    pub fn draw_map(&mut self, map: &TileMap, cam: &Camera) -> anyhow::Result<()> {
+        self.core.draw_map(self.target.frame_mut(), map, cam)
     }
 
-    pub fn clear(&mut self, clear_color: impl Into<Option<gfx::Color>>) -> anyhow::Result<()> {
+    /// Clears the window and the raycast target, drawing the horizon line
+    /// where `cam` sees it.
+    pub fn clear(
+        &mut self,
+        clear_color: impl Into<Option<gfx::Color>>,
+        cam: &Camera,
+    ) -> anyhow::Result<()> {
         let color = match clear_color.into() {
             Some(c) => c,
             None => gfx::Color::black(),
         };
         self.sdl.canvas.set_draw_color(color);
         self.sdl.canvas.clear();
-        self.target.clear_black();
+        self.core.clear(self.target.frame_mut(), cam);
         Ok(())
     }
 
-    fn raycast_screen(&mut self, player: &Player, cam: &Camera) -> anyhow::Result<()> {
-        let w = self.target.width();
-        let h = self.target.height();
-        for x in 0..w {
-            let camx = (2 * x) as f32 / (w as f32) - 1.0;
-            let ray_dir = player.dir + cam.plane * camx; // cam.plane.mul(camx) + player.dir;
+    /// Draws `sprites` on top of the last [`RaycastRenderer::draw_map`] call,
+    /// which must have been made with the same map and camera.
+    pub fn draw_sprites(&mut self, map: &TileMap, sprites: &[Sprite], cam: &Camera) {
+        self.core
+            .draw_sprites(self.target.frame_mut(), map, sprites, cam);
+    }
 
-            let mut map_pos = glm::vec2(player.pos.x as i32, player.pos.y as i32);
+    pub fn raycaster(&self) -> &Raycaster {
+        &self.core.raycaster
+    }
 
-            // length of ray from one x or y-side to next x or y-side
-            let delta_dist = {
-                let dx = if ray_dir.x == 0.0 {
-                    std::f32::INFINITY
-                } else {
-                    (1.0 / ray_dir.x).abs()
-                };
+    pub fn raycaster_mut(&mut self) -> &mut Raycaster {
+        &mut self.core.raycaster
+    }
+}
 
-                let dy = if ray_dir.y == 0.0 {
-                    std::f32::INFINITY
-                } else {
-                    (1.0 / ray_dir.y).abs()
-                };
-                glm::vec2(dx, dy)
+impl Present for RaycastRenderer {
+    fn present(&mut self) -> anyhow::Result<()> {
+        self.target.flush()?;
+        let window = self.sdl.canvas.output_size().map_err(|e| anyhow!(e))?;
+        self.target
+            .draw(&mut self.sdl.canvas, self.core.scaling.viewport(window))?;
+        self.sdl.canvas.present();
+        Ok(())
+    }
+
+    fn event(&mut self, event: &Event) -> anyhow::Result<()> {
+        if resized(event) {
+            // the renderer's size can differ from the window's on high DPI
+            // displays, which the event reports
+            let window = self.sdl.canvas.output_size().map_err(|e| anyhow!(e))?;
+            self.resize(window)?;
+        }
+        Ok(())
+    }
+}
+
+/// Software raycaster presented through wgpu instead of SDL's canvas.
+///
+/// Frames are raycast on the CPU like with [`RaycastRenderer`], then
+/// uploaded into a texture drawn as the backdrop of a [`QuadRenderer`], so
+/// quads pushed to [`WgpuRaycastRenderer::quads_mut`] (HUD, text) are
+/// composited on top in the same pass.
+pub struct WgpuRaycastRenderer {
+    quads: QuadRenderer,
+    target: WgpuTextureBuf,
+    /// Texture `target` is flushed to.
+    frame: TextureHandle,
+    core: RaycastCore,
+}
+
+impl WgpuRaycastRenderer {
+    /// Renderer drawing to `window`, see [`QuadRenderer::new`].
+    pub async fn new(window: Window, force_fallback_adapter: bool) -> anyhow::Result<Self> {
+        let quads = QuadRenderer::new(window, force_fallback_adapter).await?;
+        Ok(Self::with_quads(quads))
+    }
+
+    /// Renderer drawing to a `width` x `height` texture instead of a window,
+    /// see [`QuadRenderer::offscreen`].
+    pub async fn offscreen(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+    ) -> anyhow::Result<Self> {
+        let quads = QuadRenderer::offscreen(width, height, force_fallback_adapter).await?;
+        Ok(Self::with_quads(quads))
+    }
+
+    /// Renderer drawing frames under the quads of `quads`, whatever it
+    /// draws to.
+    pub fn with_quads(mut quads: QuadRenderer) -> Self {
+        let (width, height) = quads.size();
+        let mut target = WgpuTextureBuf::new(width, height);
+        let frame = quads.add_texture(target.create_texture(quads.device(), quads.format()));
+        let core = RaycastCore::new(target.frame_mut());
+        Self {
+            quads,
+            target,
+            frame,
+            core,
+        }
+    }
+
+    /// Number of threads the map is rendered on, one per core by default.
+    /// More than one needs the target in [`Layout::ColumnMajor`], which it's
+    /// switched to.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.core.set_threads(self.target.frame_mut(), threads);
+    }
+
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.core.threads
+    }
+
+    /// Memory layout of the frame being drawn. [`Layout::RowMajor`] also
+    /// goes back to rendering on one thread.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.core.set_layout(self.target.frame_mut(), layout);
+    }
+
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.target.frame().layout()
+    }
+
+    /// How frames are fitted to the window, reallocating the frame texture
+    /// if that changes the resolution frames are rendered at.
+    pub fn set_scaling(&mut self, scaling: Scaling) -> anyhow::Result<()> {
+        self.core.scaling = scaling;
+        self.resize(self.quads.size())
+    }
+
+    #[inline]
+    pub fn scaling(&self) -> Scaling {
+        self.core.scaling
+    }
+
+    /// Size frames are rendered at.
+    #[inline]
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.target.width(), self.target.height())
+    }
+
+    /// Reallocates the frame and its texture for a `window` sized window,
+    /// keeping the layout, see [`RaycastCore::resize`].
+    fn resize(&mut self, window: (u32, u32)) -> anyhow::Result<()> {
+        let Some((w, h)) = self.core.resize(self.target.frame(), window) else {
+            return Ok(());
+        };
+        let mut target = WgpuTextureBuf::new(w, h);
+        target.frame_mut().set_layout(self.layout());
+        let texture = target.create_texture(self.quads.device(), self.quads.format());
+        self.frame = self.quads.replace_texture(self.frame, texture)?;
+        self.target = target;
+        Ok(())
+    }
+
+    /// Raycasts `map` as seen from `cam` into the frame.
+    pub fn draw_map(&mut self, map: &TileMap, cam: &Camera) -> anyhow::Result<()> {
+        self.core.draw_map(self.target.frame_mut(), map, cam)
+    }
+
+    /// Clears the frame, drawing the horizon line where `cam` sees it.
+    pub fn clear(&mut self, cam: &Camera) {
+        self.core.clear(self.target.frame_mut(), cam);
+    }
+
+    /// Draws `sprites` on top of the last [`WgpuRaycastRenderer::draw_map`]
+    /// call, which must have been made with the same map and camera.
+    pub fn draw_sprites(&mut self, map: &TileMap, sprites: &[Sprite], cam: &Camera) {
+        self.core
+            .draw_sprites(self.target.frame_mut(), map, sprites, cam);
+    }
+
+    pub fn raycaster(&self) -> &Raycaster {
+        &self.core.raycaster
+    }
+
+    pub fn raycaster_mut(&mut self) -> &mut Raycaster {
+        &mut self.core.raycaster
+    }
+
+    /// Renderer for the layer drawn over the raycast frame, with its camera
+    /// in window pixels.
+    pub fn quads(&self) -> &QuadRenderer {
+        &self.quads
+    }
+
+    pub fn quads_mut(&mut self) -> &mut QuadRenderer {
+        &mut self.quads
+    }
+}
+
+impl Present for WgpuRaycastRenderer {
+    fn present(&mut self) -> anyhow::Result<()> {
+        let texture = self
+            .quads
+            .texture(self.frame)
+            .ok_or_else(|| anyhow!("frame texture is gone"))?;
+        self.target.flush(self.quads.queue(), texture)?;
+
+        let viewport = self.core.scaling.viewport(self.quads.size());
+        self.quads.set_backdrop(Some(Backdrop {
+            texture: self.frame,
+            viewport: Some(geom::Rect::new(
+                viewport.x() as f32,
+                viewport.y() as f32,
+                viewport.width() as f32,
+                viewport.height() as f32,
+            )),
+        }));
+        self.quads.present()
+    }
+
+    fn event(&mut self, event: &Event) -> anyhow::Result<()> {
+        if resized(event) {
+            // the drawable size can differ from the window's on high DPI
+            // displays, which the event reports
+            let Some((w, h)) = self.quads.window().map(Window::drawable_size) else {
+                return Ok(());
             };
+            // the quads stop presenting while minimized
+            self.quads.resize(w, h);
+            if w > 0 && h > 0 {
+                self.resize((w, h))?;
+            }
+        }
+        Ok(())
+    }
+}
 
-            let mut side_dist = glm::vec2(0., 0.);
-            let mut perp_wall_dist = 0.;
+/// Software raycaster. Holds what styles a frame, the view itself is passed
+/// to [`Raycaster::render`] every frame.
+#[derive(Debug, Clone, Default)]
+pub struct Raycaster {
+    atlas: Option<TextureAtlas>,
+    lighting: Lighting,
+    /// Perpendicular wall distance per screen column of the last frame.
+    z_buffer: Vec<f32>,
+    /// Scratch space for sorting sprites, kept to reuse the allocation.
+    sprite_order: Vec<(usize, f32)>,
+}
 
-            // what diretion to step in x or y-direction (either +1 or -1)
-            let mut step = glm::vec2(0, 0);
+impl Raycaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            let mut hit = 0; // was there a wall hit?
-            let mut side = 0; // was a North-South or East-West wall hit
+    /// Raycaster drawing walls with textures from `atlas`. Tiles without a
+    /// region in the atlas fall back to flat colors.
+    pub fn with_atlas(atlas: TextureAtlas) -> Self {
+        Self {
+            atlas: Some(atlas),
+            ..Default::default()
+        }
+    }
 
-            {
-                let mapx = map_pos.x as f32;
-                let mapy = map_pos.y as f32;
-                let playerx = player.pos.x;
-                let playery = player.pos.y;
+    pub fn set_atlas(&mut self, atlas: Option<TextureAtlas>) {
+        self.atlas = atlas;
+    }
 
-                if ray_dir.x < 0. {
-                    step.x = -1;
-                    side_dist.x = (playerx - mapx) * delta_dist.x;
-                } else {
-                    step.x = 1;
-                    side_dist.x = (mapx + 1.0 - playerx) * delta_dist.x;
-                }
+    pub fn atlas(&self) -> Option<&TextureAtlas> {
+        self.atlas.as_ref()
+    }
 
-                if ray_dir.y < 0.0 {
-                    step.y = -1;
-                    side_dist.y = (playery - mapy) * delta_dist.y;
-                } else {
-                    step.y = 1;
-                    side_dist.y = (mapy + 1.0 - playery) * delta_dist.y;
-                }
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        self.lighting = lighting;
+    }
+
+    pub fn lighting(&self) -> &Lighting {
+        &self.lighting
+    }
+
+    /// Distance to the wall seen by each screen column in the last rendered
+    /// frame, infinite where no wall was hit.
+    pub fn z_buffer(&self) -> &[f32] {
+        &self.z_buffer
+    }
+
+    /// Raycasts `map` as seen from `cam` into `target`, one vertical wall
+    /// slice per column. The ray for screen column x is `dir + plane * camx`
+    /// with camx going from -1 on the left to 1 on the right.
+    pub fn render<T: PixelBuffer + ?Sized>(
+        &mut self,
+        target: &mut T,
+        map: &TileMap,
+        cam: &Camera,
+    ) -> anyhow::Result<()> {
+        let w = target.width();
+        self.z_buffer.clear();
+        self.z_buffer.resize(w as usize, f32::INFINITY);
+
+        let view = View {
+            atlas: self.atlas.as_ref(),
+            lighting: &self.lighting,
+            map,
+            cam,
+            width: w,
+        };
+        view.render_band(target, 0, &mut self.z_buffer)
+    }
+
+    /// Same as [`Raycaster::render`], with the columns split into one band
+    /// per thread. Columns don't depend on each other so the frame is the
+    /// same as a single threaded one.
+    pub fn render_threaded(
+        &mut self,
+        target: &mut ColumnBuffer,
+        map: &TileMap,
+        cam: &Camera,
+        threads: usize,
+    ) -> anyhow::Result<()> {
+        let w = target.width();
+        self.z_buffer.clear();
+        self.z_buffer.resize(w as usize, f32::INFINITY);
+
+        let band_w = w
+            .div_ceil(threads.clamp(1, w.max(1) as usize) as u32)
+            .max(1);
+        let view = View {
+            atlas: self.atlas.as_ref(),
+            lighting: &self.lighting,
+            map,
+            cam,
+            width: w,
+        };
+
+        std::thread::scope(|s| {
+            let workers: Vec<_> = target
+                .bands_mut(band_w)
+                .zip(self.z_buffer.chunks_mut(band_w as usize))
+                .map(|((x0, mut band), z_buffer)| {
+                    let view = &view;
+                    s.spawn(move || view.render_band(&mut band, x0, z_buffer))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .try_for_each(|w| w.join().map_err(|_| anyhow!("render thread panicked"))?)
+        })
+    }
+
+    /// Draws `sprites` over a frame produced by [`Raycaster::render`] with the
+    /// same map and view, far to near, hiding the parts of a sprite that are behind a
+    /// wall. Sprites are textured from the atlas, nothing is drawn without one,
+    /// nor when the last frame rendered was a different width than `target`.
+    pub fn render_sprites<T: PixelBuffer + ?Sized>(
+        &mut self,
+        target: &mut T,
+        map: &TileMap,
+        sprites: &[Sprite],
+        cam: &Camera,
+    ) {
+        let Some(atlas) = self.atlas.as_ref() else {
+            return;
+        };
+
+        let (pos, dir, plane) = (cam.pos(), cam.dir(), cam.plane());
+
+        let w = target.width() as i32;
+        let h = target.height() as i32;
+        let horizon = cam.horizon(h as u32);
+        // no depths to hide sprites behind
+        if self.z_buffer.len() != w as usize {
+            return;
+        }
+
+        self.sprite_order.clear();
+        self.sprite_order.extend(
+            sprites
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, glm::distance2(&pos, &s.pos))),
+        );
+        self.sprite_order.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        // inverse of the [plane dir] camera matrix
+        let inv_det = 1.0 / (plane.x * dir.y - dir.x * plane.y);
+
+        for &(i, _) in self.sprite_order.iter() {
+            let sprite = &sprites[i];
+            let rel = sprite.pos - pos;
+
+            let transform_x = inv_det * (dir.y * rel.x - dir.x * rel.y);
+            // depth in front of the camera, same space as the z buffer
+            let transform_y = inv_det * (-plane.y * rel.x + plane.x * rel.y);
+            if transform_y <= 0. {
+                continue;
+            }
+
+            let screen_x = (w as f32 / 2.) * (1. + transform_x / transform_y);
+            let v_move = sprite.v_offset * h as f32 / transform_y;
+
+            let sprite_h = (h as f32 / transform_y * sprite.scale.y).abs();
+            let sprite_w = (h as f32 / transform_y * sprite.scale.x).abs();
+            if sprite_h < 1. || sprite_w < 1. {
+                continue;
             }
 
-            // DDA
-            while hit == 0 {
-                // jump to next map square, either in x or y-direction
-                if side_dist.x < side_dist.y {
-                    side_dist.x += delta_dist.x;
-                    map_pos.x += step.x;
-                    side = 0;
-                } else {
-                    side_dist.y += delta_dist.y;
-                    map_pos.y += step.y;
-
-                    side = 1;
+            let top = horizon as f32 - sprite_h / 2. + v_move;
+            let left = screen_x - sprite_w / 2.;
+
+            let draw_start_y = (top as i32).max(0);
+            let draw_end_y = ((top + sprite_h) as i32).min(h - 1);
+            let draw_start_x = (left as i32).max(0);
+            let draw_end_x = ((left + sprite_w) as i32).min(w - 1);
+
+            let light = map
+                .light(sprite.pos.x.floor() as i32, sprite.pos.y.floor() as i32)
+                .unwrap_or(1.);
+            let shade = self.lighting.shade(light, transform_y);
+
+            let region = sprite.texture;
+            for stripe in draw_start_x..=draw_end_x {
+                let Some(&depth) = self.z_buffer.get(stripe as usize) else {
+                    continue;
+                };
+                if transform_y >= depth {
+                    continue;
                 }
+                let tex_x = ((stripe as f32 - left) * region.w as f32 / sprite_w) as u32;
 
-                // Check if ray has hit a wall
-                if WORLD_MAP[map_pos.x as usize][map_pos.y as usize] > 0 {
-                    hit = 1;
+                for y in draw_start_y..=draw_end_y {
+                    let tex_y = ((y as f32 - top) * region.h as f32 / sprite_h) as u32;
+                    let texel = atlas.texel(region, tex_x, tex_y);
+                    if sprite.is_transparent(texel) {
+                        continue;
+                    }
+                    let [r, g, b, _] = texel.0;
+                    target.put(stripe as u32, y as u32, shade.apply(image::Rgb([r, g, b])));
                 }
             }
+        }
+    }
+
+    /// Renders a single frame of `map` into a new `width` x `height` image,
+    /// cleared the same way [`RaycastRenderer::clear`] clears the window.
+    pub fn render_frame(
+        &mut self,
+        map: &TileMap,
+        cam: &Camera,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<image::RgbImage> {
+        let mut frame = image::RgbImage::new(width, height);
+        frame.clear_black_horizon(cam.horizon(height));
+        self.render(&mut frame, map, cam)?;
+        Ok(frame)
+    }
+}
+
+/// What one frame is rendered from, shared by every band of columns.
+struct View<'a> {
+    atlas: Option<&'a TextureAtlas>,
+    lighting: &'a Lighting,
+    map: &'a TileMap,
+    cam: &'a Camera,
+    /// Width of the whole frame.
+    width: u32,
+}
+
+impl View<'_> {
+    /// Renders the columns starting at frame column `x0` into `target`,
+    /// which holds just those columns. `z_buffer` has one entry per column of
+    /// `target`.
+    fn render_band<T: PixelBuffer + ?Sized>(
+        &self,
+        target: &mut T,
+        x0: u32,
+        z_buffer: &mut [f32],
+    ) -> anyhow::Result<()> {
+        let (map, cam) = (self.map, self.cam);
+        if let Some(atlas) = self.atlas {
+            cast_floor_ceiling(target, atlas, self.lighting, map, cam, x0, self.width);
+        }
+
+        let (pos, dir, plane) = (cam.pos(), cam.dir(), cam.plane());
 
-            if side == 0 {
-                perp_wall_dist = side_dist.x - delta_dist.x;
+        let w = self.width;
+        let h = target.height();
+        let horizon = cam.horizon(h);
+
+        for x in 0..target.width() {
+            let camx = (2 * (x0 + x)) as f32 / (w as f32) - 1.0;
+            let ray_dir = dir + plane * camx;
+
+            let Some(hit) = cast_ray(map, pos, ray_dir, f32::INFINITY) else {
+                continue;
+            };
+            let (tile, perp_wall_dist) = (hit.tile, hit.perp_dist);
+            z_buffer[x as usize] = perp_wall_dist;
+
+            // full walls are lit by the cell in front of the face that was hit
+            let lit_cell = if map.thin_wall(hit.cell.x, hit.cell.y).is_some() {
+                hit.cell
             } else {
-                perp_wall_dist = side_dist.y - delta_dist.y;
-            }
+                let n = hit.normal(ray_dir);
+                hit.cell + glm::vec2(n.x as i32, n.y as i32)
+            };
+            let light = map.light(lit_cell.x, lit_cell.y).unwrap_or(1.);
+            let shade = self.lighting.shade(light, perp_wall_dist);
 
             // calc height of line to draw on screen
             let line_height = (h as f32 / perp_wall_dist) as i32;
 
             // calc loweest and highest pixel to fill in current stripe
             let h = h as i32;
-            let mut draw_start = -line_height / 2 + h / 2;
+            let mut draw_start = -line_height / 2 + horizon;
             if draw_start < 0 {
                 draw_start = 0;
             }
-            let mut draw_end = line_height / 2 + h / 2;
+            let mut draw_end = line_height / 2 + horizon;
             if draw_end >= h {
                 draw_end = h - 1;
             }
 
-            // choose wall color
-            let color = {
-                let x = map_pos.x as usize;
-                let y = map_pos.y as usize;
-                let mut col: [u8; 3] = match WORLD_MAP[x][y] {
-                    1 => [255, 0, 0],
-                    2 => [0, 255, 0],
-                    3 => [0, 0, 255],
-                    4 => [218, 112, 214], // Orchid
-                    _ => [255, 255, 255],
-                };
+            let textured = self
+                .atlas
+                .as_ref()
+                .and_then(|atlas| atlas.region(tile).map(|region| (atlas, region)));
+
+            match textured {
+                Some((atlas, region)) => {
+                    let tex_x = ((hit.wall_u * region.w as f32) as u32).min(region.w - 1);
 
-                // give x and y side different brightness
-                if side == 1 {
-                    for c in col.iter_mut() {
-                        *c = *c / 2;
+                    let column = TexColumn {
+                        atlas,
+                        region,
+                        tex_x,
+                        line_height,
+                        horizon,
+                        dark: hit.side == Side::NorthSouth,
+                        shade,
+                    };
+                    draw_tex_column(target, x, draw_start, draw_end, &column);
+                }
+                None => {
+                    let mut col = wall_color(tile);
+                    if hit.side == Side::NorthSouth {
+                        col = darken(col);
                     }
-                    // let [r, g, b] = &mut color.to_rgb();
-                    // color. = color.0[0] / 2;
-                    // color.g = color.g / 2;
-                    // color.b = color.b / 2;
+                    let col = shade.apply(image::Rgb(col));
+                    let _ = draw_vert_line(target, x as i32, draw_start, draw_end, col)?;
                 }
-
-                image::Rgb(col)
-            };
-
-            let _ = self.draw_vert_line(x as i32, draw_start, draw_end, color)?;
+            }
         }
         Ok(())
     }
+}
 
-    // Fast vertical line from (x, y1) to (x, y2) with rgb color.
-    fn draw_vert_line(
-        &mut self,
-        x: i32,
-        mut y1: i32,
-        mut y2: i32,
-        color: image::Rgb<u8>,
-    ) -> anyhow::Result<bool> {
-        let w = self.target.width() as i32;
-        let h = self.target.height() as i32;
-
-        // swap y1 and y2
-        if y2 < y1 {
-            y1 += y2;
-            y2 = y1 - y2;
-            y1 -= y2;
+// Fast vertical line from (x, y1) to (x, y2) with rgb color.
+fn draw_vert_line<T: PixelBuffer + ?Sized>(
+    target: &mut T,
+    x: i32,
+    mut y1: i32,
+    mut y2: i32,
+    color: image::Rgb<u8>,
+) -> anyhow::Result<bool> {
+    let w = target.width() as i32;
+    let h = target.height() as i32;
+
+    // swap y1 and y2
+    if y2 < y1 {
+        std::mem::swap(&mut y1, &mut y2);
+    }
+    if y2 < 0 || y1 >= h || x < 0 || x >= w {
+        // no single point of the line is on screen.
+        return Ok(false);
+    }
+
+    // clip
+    if y1 < 0 {
+        y1 = 0;
+    }
+
+    // clip
+    if y2 >= h {
+        y2 = h - 1
+    }
+
+    let x = x as u32;
+    for y in y1..=y2 {
+        let y = y as u32;
+        target.put(x, y, color);
+    }
+
+    Ok(true)
+}
+
+/// Draws the floor and ceiling textures row by row. Every screen row below
+/// the horizon sees the floor at a single distance, so the ray hit points of
+/// the leftmost and rightmost rays are interpolated across the row. The
+/// ceiling is the same row mirrored above the horizon.
+fn cast_floor_ceiling<T: PixelBuffer + ?Sized>(
+    target: &mut T,
+    atlas: &TextureAtlas,
+    lighting: &Lighting,
+    map: &TileMap,
+    cam: &Camera,
+    x0: u32,
+    frame_w: u32,
+) {
+    let (pos, dir, plane) = (cam.pos(), cam.dir(), cam.plane());
+    let h = target.height() as i32;
+    let horizon = cam.horizon(h as u32);
+
+    let ray_dir0 = dir - plane;
+    let ray_dir1 = dir + plane;
+
+    // camera sits halfway between floor and ceiling
+    let pos_z = 0.5 * h as f32;
+
+    for y in 0..h {
+        let is_floor = y > horizon;
+        // current row relative to the horizon
+        let p = if is_floor { y - horizon } else { horizon - y };
+        if p == 0 {
+            continue;
         }
-        if y2 < 0 || y1 >= h as i32 || x < 0 || x >= w as i32 {
-            // no single point of the line is on screen.
-            return Ok(false);
+
+        // horizontal distance from the camera to the floor for this row
+        let row_distance = pos_z / p as f32;
+        let fog = lighting.fog_amount(row_distance);
+
+        // world space step per screen column
+        let floor_step = (ray_dir1 - ray_dir0) * (row_distance / frame_w as f32);
+        let row_start = pos + ray_dir0 * row_distance;
+
+        for x in 0..target.width() {
+            let floor = row_start + floor_step * (x0 + x) as f32;
+            let cell_x = floor.x.floor() as i32;
+            let cell_y = floor.y.floor() as i32;
+            let (u, v) = (floor.x - cell_x as f32, floor.y - cell_y as f32);
+
+            let tile = if is_floor {
+                map.floor(cell_x, cell_y)
+            } else {
+                map.ceiling(cell_x, cell_y)
+            };
+            let Some(region) = tile.and_then(|t| atlas.region(t)) else {
+                continue;
+            };
+
+            let light = map.light(cell_x, cell_y).unwrap_or(1.);
+            let shade = lighting.shade_fogged(light, fog);
+            let [r, g, b, _] = atlas.sample(region, u, v).0;
+            target.put(x, y as u32, shade.apply(image::Rgb([r, g, b])));
         }
+    }
+}
+
+/// One screen column worth of wall texture.
+struct TexColumn<'a> {
+    atlas: &'a TextureAtlas,
+    region: AtlasRegion,
+    tex_x: u32,
+    /// Unclipped height of the wall slice on screen.
+    line_height: i32,
+    /// Screen row the middle of the wall slice is on.
+    horizon: i32,
+    dark: bool,
+    shade: Shade,
+}
 
-        // clip
-        if y1 < 0 {
-            y1 = 0;
+// Vertical line from (x, y1) to (x, y2) sampling a single column of a wall
+// texture. y1 and y2 must already be clipped to the target.
+fn draw_tex_column<T: PixelBuffer + ?Sized>(
+    target: &mut T,
+    x: u32,
+    y1: i32,
+    y2: i32,
+    col: &TexColumn,
+) {
+    let line_height = col.line_height.max(1);
+
+    // how much to move in the texture per screen pixel
+    let step = col.region.h as f32 / line_height as f32;
+    let mut tex_pos = (y1 - col.horizon + line_height / 2) as f32 * step;
+
+    for y in y1..=y2 {
+        let tex_y = tex_pos as u32;
+        tex_pos += step;
+
+        let [r, g, b, _] = col.atlas.texel(col.region, col.tex_x, tex_y).0;
+        let mut rgb = [r, g, b];
+        if col.dark {
+            rgb = darken(rgb);
         }
+        target.put(x, y as u32, col.shade.apply(image::Rgb(rgb)));
+    }
+}
 
-        // clip
-        if y2 >= w as i32 {
-            y2 = h as i32 - 1
+// give x and y sides different brightness
+#[inline]
+fn darken([r, g, b]: [u8; 3]) -> [u8; 3] {
+    [r / 2, g / 2, b / 2]
+}
+
+/// Color of untextured walls, also uploaded for the GPU raycaster by
+/// [`crate::gpu_raycast::GpuRaycaster::new`].
+pub(crate) fn wall_color(tile: Tile) -> [u8; 3] {
+    match tile.id() {
+        1 => [255, 0, 0],
+        2 => [0, 255, 0],
+        3 => [0, 0, 255],
+        4 => [218, 112, 214], // Orchid
+        _ => [255, 255, 255],
+    }
+}
+
+/// Walks around [`WORLD_MAP`], arrow keys or WASD and mouse to move, space
+/// or E to open and close doors, F2 to switch between full and low
+/// resolution rendering. A crosshair is drawn over the frame with quads.
+struct Demo {
+    map: TileMap,
+    cam: Camera,
+    /// Camera as of the previous update, rendered frames blend towards `cam`.
+    prev_cam: Camera,
+    mouse_look: MouseLook,
+    /// Tiles per second.
+    speed: f32,
+    /// Radians per second.
+    turn_speed: f32,
+    /// Use key pressed since the last update.
+    use_pressed: bool,
+    /// Set when F2 is pressed, applied on the next render.
+    next_scaling: Option<Scaling>,
+    scaling: Scaling,
+}
+
+/// Low resolution the demo can switch to.
+const RETRO_W: u32 = 320;
+const RETRO_H: u32 = 200;
+
+/// Furthest away a door can be opened from, in tiles.
+const USE_DIST: f32 = 1.5;
+
+impl Game for Demo {
+    type Renderer = WgpuRaycastRenderer;
+
+    fn event(&mut self, event: &Event) -> Control {
+        match event {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => Control::Quit,
+            Event::KeyDown {
+                keycode: Some(Keycode::Space | Keycode::E),
+                repeat: false,
+                ..
+            } => {
+                self.use_pressed = true;
+                Control::Continue
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F2),
+                repeat: false,
+                ..
+            } => {
+                self.scaling = match self.scaling {
+                    Scaling::Native => Scaling::Integer {
+                        width: RETRO_W,
+                        height: RETRO_H,
+                    },
+                    Scaling::Integer { width, height } => Scaling::Letterbox { width, height },
+                    Scaling::Letterbox { .. } => Scaling::Native,
+                };
+                self.next_scaling = Some(self.scaling);
+                Control::Continue
+            }
+            _ => Control::Continue,
         }
+    }
 
-        // self.sdl.canvas.set_draw_color(*color);
-        // self.sdl
-        //     .canvas
-        //     .draw_line(Point::new(x, y1), Point::new(x, y2));
-        // for y in y1..=y2 {
-        //     self.sdl
-        //         .canvas
-        //         .draw_point(Point::new(x, y))
-        //         .map_err(|e| anyhow!(e))?;
-        // }
-        //
-
-        let x = x as u32;
-        for y in y1..=y2 {
-            let y = y as u32;
-            self.target.put(x, y, color);
+    fn update(&mut self, dt: f32, input: &InputState) -> Control {
+        self.prev_cam = self.cam.clone();
+
+        if std::mem::take(&mut self.use_pressed) {
+            if let Some(hit) = cast_ray(&self.map, self.cam.pos(), self.cam.dir(), USE_DIST) {
+                self.map.toggle_door(hit.cell.x, hit.cell.y);
+            }
         }
+        self.map.update_doors(dt);
 
-        Ok(true)
+        let cam = &mut self.cam;
+        self.mouse_look.apply(cam, input);
+        cam.rotate(input.turn() * self.turn_speed * dt);
+        cam.move_forward(&self.map, input.forward() * self.speed * dt);
+        cam.strafe(&self.map, input.strafe() * self.speed * dt);
+        Control::Continue
     }
 
-    fn present(&mut self) -> anyhow::Result<()> {
-        self.target.flush()?;
-        self.target.draw(&mut self.sdl.canvas)?;
-        // self.sdl
-        //     .canvas
-        //     .copy(
-        //         &self.target,
-        //         None,
-        //         None
-        //     )
-        //     .map_err(|e| anyhow!(e))?;
-        self.sdl.canvas.present();
+    fn render(&mut self, r: &mut WgpuRaycastRenderer, alpha: f32) -> anyhow::Result<()> {
+        if let Some(scaling) = self.next_scaling.take() {
+            r.set_scaling(scaling)?;
+        }
+        let cam = self.prev_cam.lerp(&self.cam, alpha);
+        r.clear(&cam);
+        r.draw_map(&self.map, &cam)?;
+        draw_crosshair(r.quads_mut());
         Ok(())
     }
 }
 
-struct Player {
-    pos: glm::Vec2,
-    dir: glm::Vec2,
-    speed: f32,
-}
-
-struct Camera {
-    plane: glm::Vec2,
-    speed: f32,
+/// Small cross in the middle of the window.
+fn draw_crosshair(quads: &mut QuadRenderer) {
+    let center = quads.camera().position;
+    quads.begin();
+    for half_size in [glm::vec2(8., 1.), glm::vec2(1., 8.)] {
+        let mut xform = TransformBuilder::new();
+        xform.position(center).scale(half_size);
+        quads.push_quad(&Quad {
+            xform: xform.build(),
+            color: glm::vec4(1., 1., 1., 0.8),
+            ..Default::default()
+        });
+    }
+    quads.end();
 }
 
 pub fn run() -> anyhow::Result<()> {
-    // Show logs from wgpu
-
     let sdl_context = sdl2::init().map_err(|e| anyhow!(e))?;
     let video_subsystem = sdl_context.video().map_err(|e| anyhow!(e))?;
     let window = video_subsystem
         .window("Raw Window Handle Example", 800, 600)
         // .position_centered()
-        // .resizable()
+        .resizable()
         .vulkan()
         .build()?;
-    let (width, height) = window.size();
-
-    let mut r = RaycastRenderer::new(sdl_context, window)?;
-
-    let mut player = Player {
-        pos: glm::vec2(22., 12.),
-        dir: glm::vec2(-1., 0.),
-        speed: 25.,
-    };
-    let mut cam = Camera {
-        plane: glm::vec2(0., 0.66),
-        speed: 35.,
-    };
 
-    let mut last_dt = std::time::Instant::now();
-
-    'running: loop {
-        let now = std::time::Instant::now();
-        let dt = (now - last_dt).as_secs_f32();
-        last_dt = now;
-
-        for event in r.sdl.event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => {
-                    break 'running;
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Up),
-                    ..
-                } => {
-                    let next_x = (player.pos.x + player.dir.x * (player.speed * dt)) as usize;
-                    let next_y = (player.pos.y + player.dir.y * (player.speed * dt)) as usize;
-                    if WORLD_MAP[next_x][player.pos.y as usize] == 0 {
-                        player.pos.x += player.dir.x * (player.speed * dt);
-                    }
-                    if WORLD_MAP[player.pos.x as usize][next_y] == 0 {
-                        player.pos.y += player.dir.y * (player.speed * dt);
-                    }
-                }
+    let mut app = App::new(&sdl_context)?;
+    app.set_relative_mouse_mode(true);
 
-                Event::KeyDown {
-                    keycode: Some(Keycode::Down),
-                    ..
-                } => {
-                    let next_x = (player.pos.x + player.dir.x * (player.speed * dt)) as usize;
-                    let next_y = (player.pos.y + player.dir.y * (player.speed * dt)) as usize;
-                    if WORLD_MAP[next_x][player.pos.y as usize] == 0 {
-                        player.pos.x -= player.dir.x * (player.speed * dt);
-                    }
-                    if WORLD_MAP[player.pos.x as usize][next_y] == 0 {
-                        player.pos.y -= player.dir.y * (player.speed * dt);
-                    }
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Right),
-                    ..
-                } => {
-                    let old_dx = player.dir.x;
-                    let rot_speed = cam.speed * dt;
-
-                    player.dir.x =
-                        player.dir.x * (-rot_speed).cos() - player.dir.y * (-rot_speed).sin();
-                    player.dir.y = old_dx * (-rot_speed).sin() + player.dir.y * (-rot_speed).cos();
-
-                    let old_px = cam.plane.x;
-                    cam.plane.x =
-                        cam.plane.x * (-rot_speed).cos() - cam.plane.y * (-rot_speed).sin();
-                    cam.plane.y = old_px * (-rot_speed).sin() + cam.plane.y * (-rot_speed).cos();
-                }
-
-                Event::KeyDown {
-                    keycode: Some(Keycode::Left),
-                    ..
-                } => {
-                    let old_dx = player.dir.x;
-                    let rot_speed = cam.speed * dt;
+    let mut r = smol::block_on(WgpuRaycastRenderer::new(window, false))?;
+    r.raycaster_mut().set_lighting(
+        Lighting::default().with_fog(Fog::Exponential { density: 0.08 }, image::Rgb([0, 0, 0])),
+    );
 
-                    player.dir.x =
-                        player.dir.x * (rot_speed).cos() - player.dir.y * (rot_speed).sin();
-                    player.dir.y = old_dx * (rot_speed).sin() + player.dir.y * (rot_speed).cos();
+    let mut map = TileMap::from_rows(&WORLD_MAP)?;
+    // door into the small room
+    map.set_door(
+        8,
+        8,
+        Tile(2),
+        Door::new(ThinWall::centered(Orientation::Horizontal)),
+    );
 
-                    let old_px = cam.plane.x;
-                    cam.plane.x = cam.plane.x * (rot_speed).cos() - cam.plane.y * (rot_speed).sin();
-                    cam.plane.y = old_px * (rot_speed).sin() + cam.plane.y * (rot_speed).cos();
-                }
-                e => {
-                    dbg!(e);
-                }
-            }
-        }
+    let cam = Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.));
+    let mut demo = Demo {
+        map,
+        prev_cam: cam.clone(),
+        cam,
+        mouse_look: MouseLook {
+            vertical: true,
+            ..Default::default()
+        },
+        speed: 5.,
+        turn_speed: 3.,
+        use_pressed: false,
+        next_scaling: None,
+        scaling: Scaling::Native,
+    };
 
-        r.clear(None)?;
-        // r.raycast_screen(&player, &cam)?;
-        r.present()?;
-    }
-    Ok(())
+    app.run(&mut demo, &mut r)
 }
@@ -0,0 +1,322 @@
+// Per pixel port of the software raycaster in raycast.rs and ray.rs. Every
+// pixel casts the ray of its column, so a frame matches `render_frame` up to
+// float rounding.
+
+struct Globals {
+    pos: vec2<f32>,
+    dir: vec2<f32>,
+    plane: vec2<f32>,
+    size: vec2<u32>,
+    map_size: vec2<u32>,
+    horizon: i32,
+    fog_kind: u32,
+    fog_params: vec2<f32>,
+    ambient: f32,
+    fog_color: vec3<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> g: Globals;
+// one texel per cell, see `GpuRaycaster::upload_map`
+@group(0) @binding(1)
+var cells: texture_2d<u32>;
+// atlas region (x, y, w, h) of every tile id, w is 0 for tiles without one
+@group(0) @binding(2)
+var regions: texture_2d<u32>;
+@group(0) @binding(3)
+var atlas: texture_2d<f32>;
+// color of untextured walls for every tile id, see `GpuRaycaster::new`
+@group(0) @binding(4)
+var<uniform> palette: array<vec4<u32>, 256>;
+
+// stands in for the infinite distances of rays parallel to an axis
+const FAR: f32 = 1e30;
+
+const SHAPE_NONE: u32 = 0u;
+const SHAPE_THIN: u32 = 1u;
+const SHAPE_DOOR: u32 = 2u;
+
+struct Cell {
+    tile: u32,
+    floor: u32,
+    ceiling: u32,
+    shape: u32,
+    vertical: bool,
+    light: f32,
+    offset: f32,
+    // how far a door has slid open
+    amount: f32,
+};
+
+struct Hit {
+    found: bool,
+    tile: u32,
+    // hit a north or south face
+    north_south: bool,
+    thin: bool,
+    cell: vec2<i32>,
+    perp_dist: f32,
+    wall_u: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    // a single triangle covering the screen
+    let uv = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn in_map(c: vec2<i32>) -> bool {
+    return c.x >= 0 && c.y >= 0 && c.x < i32(g.map_size.x) && c.y < i32(g.map_size.y);
+}
+
+fn load_cell(c: vec2<i32>) -> Cell {
+    let t = textureLoad(cells, c, 0);
+    var cell: Cell;
+    cell.tile = t.r & 0xffu;
+    cell.floor = (t.r >> 8u) & 0xffu;
+    cell.ceiling = (t.r >> 16u) & 0xffu;
+    cell.shape = (t.r >> 24u) & 3u;
+    cell.vertical = ((t.r >> 26u) & 1u) == 1u;
+    cell.light = bitcast<f32>(t.g);
+    cell.offset = bitcast<f32>(t.b);
+    cell.amount = bitcast<f32>(t.a);
+    return cell;
+}
+
+fn region(tile: u32) -> vec4<u32> {
+    return textureLoad(regions, vec2<i32>(i32(tile), 0), 0);
+}
+
+// texel of `r` with channels from 0 to 255, clamped to the region
+fn texel(r: vec4<u32>, tx: u32, ty: u32) -> vec3<f32> {
+    let p = vec2<u32>(r.x + min(tx, r.z - 1u), r.y + min(ty, r.w - 1u));
+    return floor(textureLoad(atlas, vec2<i32>(p), 0).rgb * 255.0 + 0.5);
+}
+
+fn fog_amount(dist: f32) -> f32 {
+    switch g.fog_kind {
+        case 1u: {
+            let start = g.fog_params.x;
+            let end = g.fog_params.y;
+            if end <= start {
+                return select(0.0, 1.0, dist >= end);
+            }
+            return clamp((dist - start) / (end - start), 0.0, 1.0);
+        }
+        case 2u: {
+            return 1.0 - exp(-g.fog_params.x * max(dist, 0.0));
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
+// `Shade::apply`
+fn shade(color: vec3<f32>, light: f32, fog: f32) -> vec3<f32> {
+    let ambient = clamp(g.ambient, 0.0, 1.0);
+    let brightness = ambient + (1.0 - ambient) * clamp(light, 0.0, 1.0);
+    if brightness >= 1.0 && fog <= 0.0 {
+        return color;
+    }
+    let lit = brightness * (1.0 - fog);
+    return min(floor(color * lit + g.fog_color * fog + 0.5), vec3<f32>(255.0));
+}
+
+// `cast_ray` with no distance limit
+fn cast_ray(origin: vec2<f32>, dir: vec2<f32>) -> Hit {
+    var hit: Hit;
+    hit.found = false;
+
+    var c = vec2<i32>(floor(origin));
+    let delta = vec2<f32>(
+        select(abs(1.0 / dir.x), FAR, dir.x == 0.0),
+        select(abs(1.0 / dir.y), FAR, dir.y == 0.0),
+    );
+    var step = vec2<i32>(1, 1);
+    var side_dist = vec2<f32>(FAR, FAR);
+    if dir.x < 0.0 {
+        step.x = -1;
+        side_dist.x = (origin.x - f32(c.x)) * delta.x;
+    } else if dir.x > 0.0 {
+        side_dist.x = (f32(c.x) + 1.0 - origin.x) * delta.x;
+    }
+    if dir.y < 0.0 {
+        step.y = -1;
+        side_dist.y = (origin.y - f32(c.y)) * delta.y;
+    } else if dir.y > 0.0 {
+        side_dist.y = (f32(c.y) + 1.0 - origin.y) * delta.y;
+    }
+
+    // a ray can't cross more cells than this before leaving the map
+    let max_steps = i32(g.map_size.x + g.map_size.y) + 2;
+    var perp_dist = 0.0;
+    var north_south = false;
+    var along = 0.0;
+    for (var i = 0; i < max_steps; i++) {
+        if i > 0 {
+            if side_dist.x < side_dist.y {
+                perp_dist = side_dist.x;
+                side_dist.x += delta.x;
+                c.x += step.x;
+                north_south = false;
+            } else {
+                perp_dist = side_dist.y;
+                side_dist.y += delta.y;
+                c.y += step.y;
+                north_south = true;
+            }
+        }
+        if !in_map(c) || perp_dist >= FAR {
+            return hit;
+        }
+
+        let cell = load_cell(c);
+        if cell.tile == 0u {
+            continue;
+        }
+
+        if cell.shape == SHAPE_NONE {
+            // full walls are hit where the ray enters them, never in the
+            // cell the ray starts in
+            if i == 0 {
+                continue;
+            }
+            let point = origin + dir * perp_dist;
+            let a = select(point.x, point.y, !north_south);
+            hit.found = true;
+            hit.north_south = north_south;
+            hit.perp_dist = perp_dist;
+            along = a - floor(a);
+        } else {
+            var t: f32;
+            var a: f32;
+            var ns: bool;
+            if cell.vertical {
+                if dir.x == 0.0 {
+                    continue;
+                }
+                t = (f32(c.x) + cell.offset - origin.x) / dir.x;
+                a = origin.y + dir.y * t - f32(c.y);
+                ns = false;
+            } else {
+                if dir.y == 0.0 {
+                    continue;
+                }
+                t = (f32(c.y) + cell.offset - origin.y) / dir.y;
+                a = origin.x + dir.x * t - f32(c.x);
+                ns = true;
+            }
+            if t <= 0.0 || t < perp_dist || a < 0.0 || a > 1.0 {
+                continue;
+            }
+            if cell.shape == SHAPE_DOOR {
+                if a < cell.amount {
+                    continue;
+                }
+                a -= cell.amount;
+            }
+            hit.found = true;
+            hit.thin = true;
+            hit.north_south = ns;
+            hit.perp_dist = t;
+            along = a;
+        }
+        hit.tile = cell.tile;
+        hit.cell = c;
+        break;
+    }
+
+    if hit.found {
+        let flip = select(dir.x > 0.0, dir.y < 0.0, hit.north_south);
+        hit.wall_u = select(along, 1.0 - along, flip);
+    }
+    return hit;
+}
+
+// floor or ceiling seen at (x, y), `color` if there's none
+fn floor_ceiling(x: i32, y: i32, color: vec3<f32>) -> vec3<f32> {
+    let is_floor = y > g.horizon;
+    let p = select(g.horizon - y, y - g.horizon, is_floor);
+    if p == 0 {
+        return color;
+    }
+
+    let ray_dir0 = g.dir - g.plane;
+    let ray_dir1 = g.dir + g.plane;
+    let row_distance = 0.5 * f32(g.size.y) / f32(p);
+    let floor_step = (ray_dir1 - ray_dir0) * (row_distance / f32(g.size.x));
+    let row_start = g.pos + ray_dir0 * row_distance;
+    let point = row_start + floor_step * f32(x);
+
+    let c = vec2<i32>(floor(point));
+    if !in_map(c) {
+        return color;
+    }
+    let cell = load_cell(c);
+    let r = region(select(cell.ceiling, cell.floor, is_floor));
+    if r.z == 0u {
+        return color;
+    }
+
+    let uv = point - vec2<f32>(c);
+    let tx = u32(max(uv.x * f32(r.z), 0.0));
+    let ty = u32(max(uv.y * f32(r.w), 0.0));
+    return shade(texel(r, tx, ty), cell.light, fog_amount(row_distance));
+}
+
+@fragment
+fn fs_main(@builtin(position) frag: vec4<f32>) -> @location(0) vec4<f32> {
+    let x = i32(frag.x);
+    let y = i32(frag.y);
+    let h = i32(g.size.y);
+
+    // black with a white horizon, like `render_frame`
+    var color = select(vec3<f32>(0.0), vec3<f32>(255.0), y == g.horizon);
+    color = floor_ceiling(x, y, color);
+
+    let camx = f32(2 * x) / f32(g.size.x) - 1.0;
+    let ray_dir = g.dir + g.plane * camx;
+    let hit = cast_ray(g.pos, ray_dir);
+    if hit.found {
+        let line_height = i32(min(f32(h) / hit.perp_dist, 1e9));
+        let draw_start = max(-line_height / 2 + g.horizon, 0);
+        let draw_end = min(line_height / 2 + g.horizon, h - 1);
+
+        if y >= draw_start && y <= draw_end {
+            // full walls are lit by the cell in front of the face that was hit
+            var lit_cell = hit.cell;
+            if !hit.thin {
+                if hit.north_south {
+                    lit_cell.y -= i32(sign(ray_dir.y));
+                } else {
+                    lit_cell.x -= i32(sign(ray_dir.x));
+                }
+            }
+            var light = 1.0;
+            if in_map(lit_cell) {
+                light = load_cell(lit_cell).light;
+            }
+
+            var wall: vec3<f32>;
+            let r = region(hit.tile);
+            if r.z > 0u {
+                let tex_x = min(u32(hit.wall_u * f32(r.z)), r.z - 1u);
+                let lh = max(line_height, 1);
+                let step = f32(r.w) / f32(lh);
+                let tex_pos = f32(y - g.horizon + lh / 2) * step;
+                wall = texel(r, tex_x, u32(max(tex_pos, 0.0)));
+            } else {
+                wall = vec3<f32>(palette[hit.tile].rgb);
+            }
+            // give x and y sides different brightness
+            if hit.north_south {
+                wall = floor(wall / 2.0);
+            }
+            color = shade(wall, light, fog_amount(hit.perp_dist));
+        }
+    }
+
+    return vec4<f32>(color / 255.0, 1.0);
+}
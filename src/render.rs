@@ -1,56 +1,173 @@
-use std::sync::Arc;
+use std::rc::Rc;
 
 use anyhow::bail;
+use nalgebra_glm as glm;
 use sdl2::video::Window;
 
-use crate::shader::Shader;
+use crate::{
+    app::Present,
+    camera::Camera2D,
+    geom::{Quad, QuadBuffer, QuadDraw, Rect},
+    gfx::{Texture, TextureHandle, TextureType, Vert2D},
+    math::TransformBuilder,
+    shader::{Globals, Shader},
+};
 
-pub struct DeviceSurface {
-    pub device: wgpu::Device,
+/// Quads the vertex and index buffers start out with room for.
+const INITIAL_QUADS: u64 = 64;
+
+/// Format of the texture an offscreen [`QuadRenderer`] draws to.
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// No adapter could be found for a [`QuadRenderer`], as when there's no GPU
+/// and no fallback adapter was allowed.
+#[derive(Debug, thiserror::Error)]
+#[error("No Adapter Found.")]
+pub struct NoAdapter;
+
+/// A [`TextureHandle`] that wasn't made by the [`QuadRenderer`] it was used
+/// with.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown texture {0:?}, textures must be added with QuadRenderer::add_texture")]
+pub struct UnknownTexture(pub TextureHandle);
+
+/// Texture drawn under every quad of a [`QuadRenderer`], filling its
+/// viewport whatever the camera. For frames rendered some other way, like
+/// the software raycaster's, with the quads as a layer on top.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backdrop {
+    pub texture: TextureHandle,
+    /// Part of the target covered, in pixels from the top left corner. All
+    /// of it if `None`. It may reach past the target's edges, which crops
+    /// the texture.
+    pub viewport: Option<Rect>,
+}
+
+pub struct WindowSurface {
     pub surface: wgpu::Surface,
     pub config: wgpu::SurfaceConfiguration,
-    pub queue: wgpu::Queue,
-    pub window: Arc<Window>,
+    pub window: Rc<Window>,
+}
+
+/// What a [`QuadRenderer`] draws to.
+enum RenderTarget {
+    Window(WindowSurface),
+    /// Offscreen texture, read back with [`QuadRenderer::read_pixels`].
+    Texture(Texture),
 }
 
+/// Draws batches of colored and textured quads with wgpu.
+///
+/// Quads pushed between [`QuadRenderer::begin`] and [`QuadRenderer::end`]
+/// make up a batch, drawn back to front by `z_index` every time the renderer
+/// presents, with one draw call per run of quads sharing a texture.
 pub struct QuadRenderer {
-    ds: DeviceSurface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    target: RenderTarget,
     shader: Shader,
+    batch: QuadBuffer,
+    vertex_buf: wgpu::Buffer,
+    index_buf: wgpu::Buffer,
+    /// Draws uploaded by the last [`QuadRenderer::end`].
+    draws: Vec<QuadDraw>,
+    texture_layout: wgpu::BindGroupLayout,
+    /// Indexed by [`TextureHandle::id`].
+    textures: Vec<(Texture, wgpu::BindGroup)>,
+    /// 1x1 white texture that untextured quads are drawn with.
+    white: wgpu::BindGroup,
+    camera: Camera2D,
+    backdrop: Option<Backdrop>,
+    /// Draws the backdrop, with no blending and no camera.
+    backdrop_pipeline: wgpu::RenderPipeline,
+    /// Quad over the backdrop's viewport, rewritten every present.
+    backdrop_verts: wgpu::Buffer,
+    backdrop_indices: wgpu::Buffer,
+    /// Resized to nothing, as when the window is minimized. Nothing is
+    /// presented until it's resized to a real size again.
+    minimized: bool,
 }
 
 impl QuadRenderer {
-    pub async fn new(window: Window) -> anyhow::Result<Self> {
-        env_logger::init();
+    /// Renderer drawing to `window`. Adapters are picked the same way as
+    /// for [`QuadRenderer::offscreen`], which describes
+    /// `force_fallback_adapter`.
+    pub async fn new(window: Window, force_fallback_adapter: bool) -> anyhow::Result<Self> {
+        let _ = env_logger::try_init();
         let (width, height) = window.size();
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            dx12_shader_compiler: Default::default(),
-            ..Default::default()
-        });
+        let instance = instance();
         let surface = unsafe { instance.create_surface(&window)? };
-        let adapter_opt = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await;
-        let Some(adapter) = adapter_opt else {
-            bail!("No Adapter Found.")
+        let adapter = request_adapter(&instance, force_fallback_adapter, Some(&surface)).await?;
+        let (device, queue) = request_device(&adapter).await?;
+
+        let swapchain_capabilities = surface.get_capabilities(&adapter);
+        // colors are blended and shaded as sRGB, which a linear surface
+        // would show too dark, so one is only used when there's no choice
+        let swapchain_format = swapchain_capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(wgpu::TextureFormat::is_srgb)
+            .unwrap_or(swapchain_capabilities.formats[0]);
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: swapchain_format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: swapchain_capabilities.alpha_modes[0],
+            view_formats: vec![],
         };
+        surface.configure(&device, &surface_config);
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    limits: wgpu::Limits::default(),
-                    label: Some("device"),
-                    features: wgpu::Features::empty(),
-                },
-                None,
-            )
-            .await?;
+        let target = RenderTarget::Window(WindowSurface {
+            surface,
+            config: surface_config,
+            window: Rc::new(window),
+        });
+        Self::with_target(device, queue, target, swapchain_format, width, height)
+    }
+
+    /// Renderer drawing to a `width` x `height` texture instead of a window,
+    /// in [`OFFSCREEN_FORMAT`]. Nothing is shown, frames are read back with
+    /// [`QuadRenderer::read_pixels`].
+    ///
+    /// With `force_fallback_adapter` a software adapter is used, so frames
+    /// can be rendered on machines without a GPU.
+    pub async fn offscreen(
+        width: u32,
+        height: u32,
+        force_fallback_adapter: bool,
+    ) -> anyhow::Result<Self> {
+        Self::offscreen_with_format(width, height, OFFSCREEN_FORMAT, force_fallback_adapter).await
+    }
+
+    /// [`QuadRenderer::offscreen`] drawing to a `format` texture, which
+    /// must be 4 bytes per pixel to be read back.
+    pub async fn offscreen_with_format(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        force_fallback_adapter: bool,
+    ) -> anyhow::Result<Self> {
+        let (device, queue) = headless_device(force_fallback_adapter).await?;
+
+        let texture = Texture::render_target(&device, width, height, format, Some("offscreen"));
+        let target = RenderTarget::Texture(texture);
+        Self::with_target(device, queue, target, format, width, height)
+    }
 
+    /// Sets up everything but what's drawn to, `format` being its format.
+    fn with_target(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: RenderTarget,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<Self> {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
             source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
@@ -60,11 +177,12 @@ impl QuadRenderer {
 
         // (2)
         let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: 0, //std::mem::size_of::<AppState>() as u64,
+            label: Some("globals"),
+            size: Globals::buffer_size(),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
+        queue.write_buffer(&uniform_buffer, 0, &Globals::default().as_bytes()?);
 
         // (3)
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -93,28 +211,63 @@ impl QuadRenderer {
             }],
         });
 
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("quad texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let white = Texture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([255; 4]),
+            )),
+            TextureType::Diffuse,
+            Some("white"),
+        )?;
+        let white = texture_bind_group(&device, &texture_layout, &white);
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             // (4)
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[&bind_group_layout, &texture_layout],
             push_constant_ranges: &[],
         });
 
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
-
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[],
+                buffers: &[Vert2D::layout()],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: "fs_main",
-                targets: &[Some(swapchain_format.into())],
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
             }),
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
@@ -122,47 +275,512 @@ impl QuadRenderer {
             multiview: None,
         });
 
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: swapchain_format,
-            width,
-            height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: swapchain_capabilities.alpha_modes[0],
-            view_formats: vec![],
-        };
-        surface.configure(&device, &surface_config);
+        let backdrop_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("backdrop"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_screen",
+                buffers: &[Vert2D::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let mut fullscreen = QuadBuffer::empty();
+        fullscreen.push_quad(&backdrop_quad((width, height), None));
+        fullscreen.build();
+        let verts: &[u8] = bytemuck::cast_slice(fullscreen.vertex_buffer());
+        let indices: &[u8] = bytemuck::cast_slice(fullscreen.index_buffer());
+        let backdrop_verts = create_buffer(
+            &device,
+            "backdrop vertices",
+            verts.len() as u64,
+            wgpu::BufferUsages::VERTEX,
+        );
+        let backdrop_indices = create_buffer(
+            &device,
+            "backdrop indices",
+            indices.len() as u64,
+            wgpu::BufferUsages::INDEX,
+        );
+        queue.write_buffer(&backdrop_verts, 0, verts);
+        queue.write_buffer(&backdrop_indices, 0, indices);
+
+        let vertex_buf = create_buffer(
+            &device,
+            "quad vertices",
+            INITIAL_QUADS * 4 * std::mem::size_of::<Vert2D>() as u64,
+            wgpu::BufferUsages::VERTEX,
+        );
+        let index_buf = create_buffer(
+            &device,
+            "quad indices",
+            INITIAL_QUADS * 6 * std::mem::size_of::<u32>() as u64,
+            wgpu::BufferUsages::INDEX,
+        );
 
-        let ds = DeviceSurface {
-            device,
-            surface,
-            config: surface_config,
-            queue,
-            window: Arc::new(window),
-        };
         let shader = Shader {
             pipeline,
             bind_group,
             uniform_buf: uniform_buffer,
         };
-        let s = Self { ds, shader };
+        let s = Self {
+            device,
+            queue,
+            target,
+            shader,
+            batch: QuadBuffer::empty(),
+            vertex_buf,
+            index_buf,
+            draws: Vec::new(),
+            texture_layout,
+            textures: Vec::new(),
+            white,
+            camera: Camera2D::new(glm::vec2(width as f32, height as f32)),
+            backdrop: None,
+            backdrop_pipeline,
+            backdrop_verts,
+            backdrop_indices,
+            minimized: false,
+        };
         Ok(s)
     }
 
+    /// Camera quads are seen through, starting out with world coordinates
+    /// in pixels from the bottom left corner of the window.
+    #[inline]
+    pub fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    #[inline]
+    pub fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera2D) {
+        self.camera = camera;
+    }
+
+    /// Makes `texture` available to quads through the returned handle.
+    pub fn add_texture(&mut self, texture: Texture) -> TextureHandle {
+        let bind_group = texture_bind_group(&self.device, &self.texture_layout, &texture);
+        let handle = TextureHandle::new(
+            self.textures.len() as u32,
+            texture.size.width,
+            texture.size.height,
+        );
+        self.textures.push((texture, bind_group));
+        handle
+    }
+
+    /// Swaps the texture behind `handle` for `texture`, returning the handle
+    /// again with the new texture's size.
+    pub fn replace_texture(
+        &mut self,
+        handle: TextureHandle,
+        texture: Texture,
+    ) -> Result<TextureHandle, UnknownTexture> {
+        let bind_group = texture_bind_group(&self.device, &self.texture_layout, &texture);
+        let new = TextureHandle::new(handle.id(), texture.size.width, texture.size.height);
+        let Some(slot) = self.textures.get_mut(handle.id() as usize) else {
+            return Err(UnknownTexture(handle));
+        };
+        *slot = (texture, bind_group);
+        if let Some(backdrop) = &mut self.backdrop {
+            if backdrop.texture.id() == handle.id() {
+                backdrop.texture = new;
+            }
+        }
+        Ok(new)
+    }
+
+    pub fn texture(&self, handle: TextureHandle) -> Option<&Texture> {
+        self.textures.get(handle.id() as usize).map(|(t, _)| t)
+    }
+
+    #[inline]
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    #[inline]
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Starts a new batch, dropping the quads of the last one.
+    pub fn begin(&mut self) {
+        self.batch.clear();
+    }
+
+    /// Adds `quad` to the batch started by [`QuadRenderer::begin`].
+    pub fn push_quad(&mut self, quad: &Quad) {
+        self.batch.push_quad(quad);
+    }
+
+    /// Finishes the batch and uploads it, to be drawn every time the
+    /// renderer presents until the next batch ends. Presenting fails with
+    /// [`UnknownTexture`] while a quad has a texture that wasn't added with
+    /// [`QuadRenderer::add_texture`].
+    pub fn end(&mut self) {
+        self.batch.build();
+        let (device, queue) = (&self.device, &self.queue);
+        write_buffer(
+            device,
+            queue,
+            &mut self.vertex_buf,
+            bytemuck::cast_slice(self.batch.vertex_buffer()),
+        );
+        write_buffer(
+            device,
+            queue,
+            &mut self.index_buf,
+            bytemuck::cast_slice(self.batch.index_buffer()),
+        );
+        self.draws.clear();
+        self.draws.extend_from_slice(self.batch.draws());
+    }
+
+    /// Sets the texture drawn under the quads, or clears it. Presenting
+    /// fails with [`UnknownTexture`] if it wasn't added with
+    /// [`QuadRenderer::add_texture`].
+    pub fn set_backdrop(&mut self, backdrop: Option<Backdrop>) {
+        self.backdrop = backdrop;
+    }
+
+    #[inline]
+    pub fn backdrop(&self) -> Option<&Backdrop> {
+        self.backdrop.as_ref()
+    }
+
+    /// Size of what's drawn to, in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        match &self.target {
+            RenderTarget::Window(ws) => (ws.config.width, ws.config.height),
+            RenderTarget::Texture(texture) => (texture.size.width, texture.size.height),
+        }
+    }
+
+    /// Format of what's drawn to.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match &self.target {
+            RenderTarget::Window(ws) => ws.config.format,
+            RenderTarget::Texture(texture) => texture.handle.format(),
+        }
+    }
+
+    /// Uploads the backdrop's quad, placed over its viewport.
+    fn write_backdrop(&self) {
+        let Some(backdrop) = &self.backdrop else {
+            return;
+        };
+        let quad = backdrop_quad(self.size(), backdrop.viewport);
+        self.queue.write_buffer(
+            &self.backdrop_verts,
+            0,
+            bytemuck::cast_slice(&quad.calc_into_verts()),
+        );
+    }
+
+    /// Uploads the camera's view-projection for the next frame.
+    fn write_globals(&self) -> anyhow::Result<()> {
+        let globals = Globals {
+            view_proj: self.camera.view_proj(),
+        };
+        self.queue
+            .write_buffer(&self.shader.uniform_buf, 0, &globals.as_bytes()?);
+        Ok(())
+    }
+
+    /// Records a pass clearing `view` and drawing the backdrop, then the
+    /// last finished batch onto it.
+    fn encode_frame(
+        &self,
+        view: &wgpu::TextureView,
+    ) -> Result<wgpu::CommandBuffer, UnknownTexture> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("frame"),
+            });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("quads"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if let Some(backdrop) = &self.backdrop {
+                pass.set_pipeline(&self.backdrop_pipeline);
+                pass.set_bind_group(0, &self.shader.bind_group, &[]);
+                pass.set_bind_group(1, self.bind_group(backdrop.texture)?, &[]);
+                pass.set_vertex_buffer(0, self.backdrop_verts.slice(..));
+                pass.set_index_buffer(self.backdrop_indices.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..6, 0, 0..1);
+            }
+
+            if !self.draws.is_empty() {
+                pass.set_pipeline(&self.shader.pipeline);
+                pass.set_bind_group(0, &self.shader.bind_group, &[]);
+                pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+                pass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+            }
+            for draw in &self.draws {
+                let bind_group = match draw.texture {
+                    Some(texture) => self.bind_group(texture)?,
+                    None => &self.white,
+                };
+                pass.set_bind_group(1, bind_group, &[]);
+                pass.draw_indexed(draw.indices.clone(), 0, 0..1);
+            }
+        }
+        Ok(encoder.finish())
+    }
+
+    fn bind_group(&self, handle: TextureHandle) -> Result<&wgpu::BindGroup, UnknownTexture> {
+        self.textures
+            .get(handle.id() as usize)
+            .map(|(_, bind_group)| bind_group)
+            .ok_or(UnknownTexture(handle))
+    }
+
+    /// Resizes what's drawn to, and the camera's viewport with it. An
+    /// offscreen target is replaced by a new, blank texture.
+    ///
+    /// A size with no area, as a minimized window reports, keeps the old
+    /// target and stops presenting until a real size comes in.
     pub fn resize(&mut self, w: u32, h: u32) {
-        self.ds.config.width = w;
-        self.ds.config.height = h;
-        let (device, config) = (&self.ds.device, &self.ds.config);
-        self.ds.surface.configure(device, config);
+        self.minimized = w == 0 || h == 0;
+        if self.minimized {
+            return;
+        }
+        self.camera.viewport = glm::vec2(w as f32, h as f32);
+        match &mut self.target {
+            RenderTarget::Window(ws) => {
+                ws.config.width = w;
+                ws.config.height = h;
+                ws.surface.configure(&self.device, &ws.config);
+            }
+            RenderTarget::Texture(texture) => {
+                let format = texture.handle.format();
+                *texture = Texture::render_target(&self.device, w, h, format, Some("offscreen"));
+            }
+        }
     }
 
     #[inline]
     pub fn has_window(&self, window_id: u32) -> bool {
-        window_id == self.ds.window.id()
+        self.window().is_some_and(|w| w.id() == window_id)
     }
 
+    /// Window drawn to, `None` for an offscreen renderer.
     #[inline]
-    pub fn window(&self) -> &Window {
-        &self.ds.window
+    pub fn window(&self) -> Option<&Window> {
+        match &self.target {
+            RenderTarget::Window(ws) => Some(&ws.window),
+            RenderTarget::Texture(_) => None,
+        }
+    }
+
+    /// Texture an offscreen renderer draws to, which can be sampled once a
+    /// frame has been presented to it. `None` when drawing to a window.
+    #[inline]
+    pub fn target_texture(&self) -> Option<&Texture> {
+        match &self.target {
+            RenderTarget::Window(_) => None,
+            RenderTarget::Texture(texture) => Some(texture),
+        }
+    }
+
+    /// Copies the last frame presented by an offscreen renderer back from
+    /// the GPU, waiting for it to finish drawing. Errors for a renderer
+    /// drawing to a window.
+    pub fn read_pixels(&self) -> anyhow::Result<image::RgbaImage> {
+        let Some(texture) = self.target_texture() else {
+            bail!("only offscreen renderers can be read back")
+        };
+        texture.read_pixels(&self.device, &self.queue)
+    }
+}
+
+impl Present for QuadRenderer {
+    /// Draws the last finished batch, to the window or the offscreen
+    /// texture.
+    fn present(&mut self) -> anyhow::Result<()> {
+        if self.minimized {
+            return Ok(());
+        }
+        self.write_globals()?;
+        self.write_backdrop();
+        match &self.target {
+            RenderTarget::Window(ws) => {
+                let frame = match ws.surface.get_current_texture() {
+                    Ok(frame) => frame,
+                    // the window changed under the surface, the next frame
+                    // gets a fresh one
+                    Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                        ws.surface.configure(&self.device, &ws.config);
+                        return Ok(());
+                    }
+                    Err(wgpu::SurfaceError::Timeout) => return Ok(()),
+                    Err(e @ wgpu::SurfaceError::OutOfMemory) => return Err(e.into()),
+                };
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                let commands = self.encode_frame(&view)?;
+                self.queue.submit(Some(commands));
+                frame.present();
+            }
+            RenderTarget::Texture(texture) => {
+                let commands = self.encode_frame(&texture.view)?;
+                self.queue.submit(Some(commands));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Device with no window to draw to, for rendering offscreen, see
+/// [`QuadRenderer::offscreen`] for `force_fallback_adapter`.
+pub async fn headless_device(
+    force_fallback_adapter: bool,
+) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let _ = env_logger::try_init();
+
+    let adapter = request_adapter(&instance(), force_fallback_adapter, None).await?;
+    request_device(&adapter).await
+}
+
+/// Instance every renderer starts from. Backends can be picked with the
+/// `WGPU_BACKEND` environment variable.
+fn instance() -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+        dx12_shader_compiler: Default::default(),
+        ..Default::default()
+    })
+}
+
+/// Fastest adapter of `instance`, one that can present to `surface` if
+/// there is one.
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    force_fallback_adapter: bool,
+    surface: Option<&wgpu::Surface>,
+) -> anyhow::Result<wgpu::Adapter> {
+    let adapter_opt = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter,
+            compatible_surface: surface,
+        })
+        .await;
+    let Some(adapter) = adapter_opt else {
+        bail!(NoAdapter)
+    };
+    Ok(adapter)
+}
+
+/// Quad covering `viewport` of a `target` sized target, in clip space so
+/// it's drawn without the camera. It may reach past the target, the parts
+/// outside are clipped.
+fn backdrop_quad(target: (u32, u32), viewport: Option<Rect>) -> Quad {
+    let (w, h) = (target.0.max(1) as f32, target.1.max(1) as f32);
+    let Rect { x, y, w: vw, h: vh } = viewport.unwrap_or(Rect::new(0., 0., w, h));
+
+    // pixels from the top left to clip space, which spans -1 to 1 with y up
+    let center = glm::vec2((x + vw / 2.) / w * 2. - 1., 1. - (y + vh / 2.) / h * 2.);
+    let mut xform = TransformBuilder::new();
+    xform.position(center).scale(glm::vec2(vw / w, vh / h));
+    Quad {
+        xform: xform.build(),
+        color: glm::vec4(1., 1., 1., 1.),
+        ..Default::default()
+    }
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let device = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                limits: wgpu::Limits::downlevel_webgl2_defaults()
+                    .using_resolution(adapter.limits()),
+                label: Some("device"),
+                features: wgpu::Features::empty(),
+            },
+            None,
+        )
+        .await?;
+    Ok(device)
+}
+
+fn texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("quad texture"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+    })
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    size: wgpu::BufferAddress,
+    usage: wgpu::BufferUsages,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Writes `data` to the start of `buf`, first swapping it for one twice as
+/// big as needed if it doesn't fit.
+fn write_buffer(device: &wgpu::Device, queue: &wgpu::Queue, buf: &mut wgpu::Buffer, data: &[u8]) {
+    let size = data.len() as wgpu::BufferAddress;
+    if size > buf.size() {
+        let usage = buf.usage() - wgpu::BufferUsages::COPY_DST;
+        *buf = create_buffer(device, "quads", size * 2, usage);
+    }
+    if size > 0 {
+        queue.write_buffer(buf, 0, data);
     }
 }
@@ -1,5 +1,80 @@
+pub use uniforms::{Globals, RaycastGlobals};
+
 pub struct Shader {
     pub pipeline: wgpu::RenderPipeline,
     pub bind_group: wgpu::BindGroup,
     pub uniform_buf: wgpu::Buffer,
 }
+
+// the ShaderType derive generates a layout check that is never called
+#[allow(dead_code)]
+mod uniforms {
+    use encase::ShaderType;
+    use nalgebra_glm as glm;
+
+    /// Uniforms shared by every quad drawn in a frame, `Globals` in
+    /// `shader.wgsl`.
+    #[derive(Debug, Clone, ShaderType)]
+    pub struct Globals {
+        /// Takes quad positions to clip space.
+        pub view_proj: glm::Mat4,
+    }
+
+    impl Default for Globals {
+        fn default() -> Self {
+            Self {
+                view_proj: glm::identity(),
+            }
+        }
+    }
+
+    impl Globals {
+        /// Bytes the uniform buffer needs, including padding.
+        pub fn buffer_size() -> wgpu::BufferAddress {
+            Self::min_size().get()
+        }
+
+        /// Encodes `self` with the layout WGSL expects for a uniform buffer.
+        pub fn as_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            encode(self)
+        }
+    }
+
+    /// Camera, frame and lighting for a [`crate::gpu_raycast::GpuRaycaster`]
+    /// frame, `Globals` in `raycast.wgsl`.
+    #[derive(Debug, Clone, Default, ShaderType)]
+    pub struct RaycastGlobals {
+        pub pos: glm::Vec2,
+        pub dir: glm::Vec2,
+        pub plane: glm::Vec2,
+        /// Frame size in pixels.
+        pub size: glm::UVec2,
+        /// Map size in cells.
+        pub map_size: glm::UVec2,
+        /// Screen row of the horizon.
+        pub horizon: i32,
+        /// 0 for no fog, 1 for linear and 2 for exponential fog.
+        pub fog_kind: u32,
+        /// Start and end of linear fog, or the density of exponential fog.
+        pub fog_params: glm::Vec2,
+        pub ambient: f32,
+        /// Fog color with channels from 0 to 255.
+        pub fog_color: glm::Vec3,
+    }
+
+    impl RaycastGlobals {
+        pub fn buffer_size() -> wgpu::BufferAddress {
+            Self::min_size().get()
+        }
+
+        pub fn as_bytes(&self) -> anyhow::Result<Vec<u8>> {
+            encode(self)
+        }
+    }
+
+    fn encode<T: ShaderType + encase::internal::WriteInto>(value: &T) -> anyhow::Result<Vec<u8>> {
+        let mut buf = encase::UniformBuffer::new(Vec::new());
+        buf.write(value)?;
+        Ok(buf.into_inner())
+    }
+}
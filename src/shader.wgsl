@@ -1,12 +1,47 @@
+struct Globals {
+    view_proj: mat4x4<f32>,
+};
 
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@group(1) @binding(0)
+var quad_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var quad_sampler: sampler;
+
+struct VertexInput {
+    @location(0) pos: vec4<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_pos = globals.view_proj * in.pos;
+    out.color = in.color;
+    out.uv = in.uv;
+    return out;
+}
+
+// quads already in clip space, drawn the same whatever the camera
 @vertex
-fn vs_main(@builtin(vertex_index) in_vertex_index: u32) ->  @builtin(position) vec4<f32> {
-    let x = f32(i32(in_vertex_index) - 1);
-    let y = f32(i32(in_vertex_index & 1u) * 2 - 1);
-    return vec4<f32>(x, y, 0.0, 1.0);
+fn vs_screen(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_pos = in.pos;
+    out.color = in.color;
+    out.uv = in.uv;
+    return out;
 }
 
 @fragment
-fn fs_main() -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * textureSample(quad_texture, quad_sampler, in.uv);
 }
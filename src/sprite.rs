@@ -0,0 +1,58 @@
+use nalgebra_glm as glm;
+
+use crate::atlas::AtlasRegion;
+
+/// Billboard drawn into the raycast view, always facing the camera.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    /// Position on the map, in tiles.
+    pub pos: glm::Vec2,
+    /// Texture of the sprite in the raycaster's atlas.
+    pub texture: AtlasRegion,
+    /// Size relative to a wall tile, (1, 1) is as big as a wall.
+    pub scale: glm::Vec2,
+    /// Vertical offset in wall heights, positive moves the sprite down.
+    /// A sprite scaled to `s` stands on the floor with an offset of `(1 - s) / 2`.
+    pub v_offset: f32,
+    /// Texels of this color are not drawn. Texels with zero alpha are never
+    /// drawn regardless of the key.
+    pub color_key: Option<image::Rgb<u8>>,
+}
+
+impl Sprite {
+    pub fn new(pos: glm::Vec2, texture: AtlasRegion) -> Self {
+        Self {
+            pos,
+            texture,
+            scale: glm::vec2(1., 1.),
+            v_offset: 0.,
+            color_key: None,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: glm::Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_v_offset(mut self, v_offset: f32) -> Self {
+        self.v_offset = v_offset;
+        self
+    }
+
+    pub fn with_color_key(mut self, key: image::Rgb<u8>) -> Self {
+        self.color_key = Some(key);
+        self
+    }
+
+    /// Scales the sprite by `scale` and moves it down so it stands on the floor.
+    pub fn on_floor(self, scale: glm::Vec2) -> Self {
+        self.with_scale(scale).with_v_offset((1. - scale.y) / 2.)
+    }
+
+    #[inline]
+    pub fn is_transparent(&self, texel: image::Rgba<u8>) -> bool {
+        let [r, g, b, a] = texel.0;
+        a == 0 || self.color_key == Some(image::Rgb([r, g, b]))
+    }
+}
@@ -0,0 +1,536 @@
+//! Import of maps made with [Tiled](https://www.mapeditor.org/), saved as
+//! JSON (`.tmj`).
+//!
+//! Tile layers named `walls`, `floor` and `ceiling` fill the matching layers
+//! of the [`TileMap`], only `walls` is required. Tile ids are the tile's
+//! global id in Tiled unless the tile has an int `texture` property, and
+//! tiles with a bool `solid` property set to false are left out of the wall
+//! layer. Every used tile is given the region of its tileset image as its
+//! texture, so the tileset image becomes the level's atlas, and tiles with
+//! the same id must have the same image.
+//!
+//! Object layers hold the spawn point, an object whose class (or name) is
+//! `spawn` with an optional string `facing` property, and sprites, which are
+//! tile objects. Sprites take float `scale` and `v_offset` properties and the
+//! tileset's transparent color as their color key.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use nalgebra_glm as glm;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    atlas::AtlasRegion,
+    level::{compass, Level},
+    map::{Tile, TileMap},
+    sprite::Sprite,
+};
+
+/// Bits of a global tile id Tiled uses for flipping and rotating tiles.
+const GID_FLAGS: u32 = 0xf000_0000;
+
+/// Most cells an imported map may have, 2048x2048.
+pub const MAX_CELLS: usize = 1 << 22;
+
+#[derive(Debug, Error)]
+pub enum TiledError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("not supported: {0}")]
+    Unsupported(String),
+    #[error("map has no `{0}` tile layer")]
+    MissingLayer(&'static str),
+    #[error("map is {width}x{height}, it must have between 1 and {MAX_CELLS} cells")]
+    MapSize { width: u32, height: u32 },
+    #[error("tiles are {width}x{height} pixels, neither can be 0")]
+    TileSize { width: u32, height: u32 },
+    #[error("layer `{layer}` is {width}x{height}, the map is {map_width}x{map_height}")]
+    LayerSize {
+        layer: String,
+        width: u32,
+        height: u32,
+        map_width: u32,
+        map_height: u32,
+    },
+    #[error("layer `{layer}` has {len} tiles, {expected} expected")]
+    LayerData {
+        layer: String,
+        len: usize,
+        expected: usize,
+    },
+    #[error("tile {gid} is not in any tileset")]
+    UnknownGid { gid: u32 },
+    #[error("tile {gid} maps to id {id}, ids must be between 1 and 255")]
+    TileIdOutOfRange { gid: u32, id: i64 },
+    #[error("tile {gid} has texture id {id}, which another tile with a different image uses")]
+    TextureConflict { gid: u32, id: u8 },
+    #[error("map has no object with class `spawn`")]
+    MissingSpawn,
+    #[error("spawn point ({x}, {y}) is not in an empty cell")]
+    SpawnInWall { x: f32, y: f32 },
+    #[error("unknown facing `{0}`, expected north, south, east or west")]
+    BadFacing(String),
+}
+
+/// Reads a Tiled JSON map. External tilesets and the tileset image are
+/// resolved relative to the map file.
+pub fn load(path: impl AsRef<Path>) -> Result<Level, TiledError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    import(&std::fs::read_to_string(path)?, Some(dir))
+}
+
+/// Parses a Tiled JSON map. Maps using external tilesets must be read with
+/// [`load`] instead.
+pub fn parse(json: &str) -> Result<Level, TiledError> {
+    import(json, None)
+}
+
+fn import(json: &str, dir: Option<&Path>) -> Result<Level, TiledError> {
+    let mut tmj: TmjMap = serde_json::from_str(json)?;
+    if tmj.infinite {
+        return Err(TiledError::Unsupported("infinite maps".into()));
+    }
+    for ts in &mut tmj.tilesets {
+        ts.resolve(dir)?;
+    }
+    tmj.tilesets.sort_by_key(|ts| ts.firstgid);
+
+    let mut images = tmj.tilesets.iter().filter_map(|ts| ts.image.as_ref());
+    if let Some(first) = images.next() {
+        if images.any(|image| image != first) {
+            return Err(TiledError::Unsupported(
+                "tilesets with different images, the level has one atlas".into(),
+            ));
+        }
+    }
+
+    let mut importer = Importer {
+        tmj: &tmj,
+        tiles: HashMap::new(),
+        textures: Vec::new(),
+    };
+    importer.build(dir)
+}
+
+struct Importer<'a> {
+    tmj: &'a TmjMap,
+    /// Tile each used global id was mapped to, and whether it's solid.
+    tiles: HashMap<u32, (Tile, bool)>,
+    textures: Vec<(Tile, AtlasRegion)>,
+}
+
+impl<'a> Importer<'a> {
+    fn build(&mut self, dir: Option<&Path>) -> Result<Level, TiledError> {
+        let (w, h) = (self.tmj.width, self.tmj.height);
+        // checked before allocating anything, the sizes come from the file
+        if !TileMap::cell_count(w, h).is_some_and(|n| (1..=MAX_CELLS).contains(&n)) {
+            return Err(TiledError::MapSize {
+                width: w,
+                height: h,
+            });
+        }
+        let (tw, th) = (self.tmj.tilewidth, self.tmj.tileheight);
+        if tw == 0 || th == 0 {
+            return Err(TiledError::TileSize {
+                width: tw,
+                height: th,
+            });
+        }
+        let mut layers = Vec::new();
+        flatten(&self.tmj.layers, &mut layers);
+
+        let mut map = TileMap::new(w, h);
+        let mut has_walls = false;
+        let mut spawn = None;
+        let mut sprites = Vec::new();
+
+        for layer in layers {
+            match layer {
+                TmjLayer::TileLayer {
+                    name,
+                    width,
+                    height,
+                    data,
+                    encoding,
+                } => {
+                    let set: fn(&mut TileMap, i32, i32, Tile) -> bool =
+                        match name.to_lowercase().as_str() {
+                            "walls" => {
+                                has_walls = true;
+                                TileMap::set
+                            }
+                            "floor" => TileMap::set_floor,
+                            "ceiling" => TileMap::set_ceiling,
+                            _ => continue,
+                        };
+                    if (*width, *height) != (w, h) {
+                        return Err(TiledError::LayerSize {
+                            layer: name.clone(),
+                            width: *width,
+                            height: *height,
+                            map_width: w,
+                            map_height: h,
+                        });
+                    }
+                    let Some(TmjData::Gids(gids)) = data else {
+                        return Err(TiledError::Unsupported(format!(
+                            "{} layer data, save the map with CSV tile layers",
+                            encoding.as_deref().unwrap_or("chunked")
+                        )));
+                    };
+                    let expected = w as usize * h as usize;
+                    if gids.len() != expected {
+                        return Err(TiledError::LayerData {
+                            layer: name.clone(),
+                            len: gids.len(),
+                            expected,
+                        });
+                    }
+                    let walls = name.eq_ignore_ascii_case("walls");
+                    for (i, gid) in gids.iter().enumerate() {
+                        let gid = gid & !GID_FLAGS;
+                        if gid == 0 {
+                            continue;
+                        }
+                        let (tile, solid) = self.tile(gid)?;
+                        if walls && !solid {
+                            continue;
+                        }
+                        let (x, y) = ((i as u32 % w) as i32, (i as u32 / w) as i32);
+                        set(&mut map, x, y, tile);
+                    }
+                }
+                TmjLayer::ObjectGroup { objects } => {
+                    for obj in objects {
+                        if obj.is_spawn() {
+                            spawn = Some(self.spawn(obj)?);
+                        } else if let Some(gid) = obj.gid {
+                            sprites.push(self.sprite(obj, gid & !GID_FLAGS)?);
+                        }
+                    }
+                }
+                TmjLayer::Group { .. } | TmjLayer::Other => {}
+            }
+        }
+
+        if !has_walls {
+            return Err(TiledError::MissingLayer("walls"));
+        }
+        let (spawn, facing) = spawn.ok_or(TiledError::MissingSpawn)?;
+        if map.is_solid(spawn.x.floor() as i32, spawn.y.floor() as i32) {
+            return Err(TiledError::SpawnInWall {
+                x: spawn.x,
+                y: spawn.y,
+            });
+        }
+
+        let atlas = self
+            .tmj
+            .tilesets
+            .iter()
+            .find_map(|ts| ts.image.as_ref())
+            .map(|image| match dir {
+                Some(dir) => dir.join(image),
+                None => PathBuf::from(image),
+            });
+
+        Ok(Level {
+            name: self
+                .tmj
+                .properties
+                .iter()
+                .find(|p| p.name == "name")
+                .and_then(|p| p.value.as_str())
+                .map(str::to_string),
+            map,
+            spawn,
+            facing,
+            atlas,
+            textures: std::mem::take(&mut self.textures),
+            sprites,
+        })
+    }
+
+    /// Tile a global id maps to and whether it's solid, adding its texture
+    /// the first time it's seen.
+    fn tile(&mut self, gid: u32) -> Result<(Tile, bool), TiledError> {
+        if let Some(t) = self.tiles.get(&gid) {
+            return Ok(*t);
+        }
+
+        let (ts, local) = self.tileset(gid)?;
+        let props = ts.properties(local);
+        let id = prop(props, "texture")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(gid as i64);
+        if !(1..=255).contains(&id) {
+            return Err(TiledError::TileIdOutOfRange { gid, id });
+        }
+        let tile = Tile(id as u8);
+        let solid = prop(props, "solid")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let region = ts.region(local)?;
+        match self.textures.iter().find(|(t, _)| *t == tile) {
+            Some((_, r)) if *r != region => {
+                return Err(TiledError::TextureConflict { gid, id: tile.id() });
+            }
+            Some(_) => {}
+            None => self.textures.push((tile, region)),
+        }
+        self.tiles.insert(gid, (tile, solid));
+        Ok((tile, solid))
+    }
+
+    /// Tileset holding `gid` and the tile's id inside of it.
+    fn tileset(&self, gid: u32) -> Result<(&'a TmjTileset, u32), TiledError> {
+        let tmj: &'a TmjMap = self.tmj;
+        tmj.tilesets
+            .iter()
+            .rev()
+            .find(|ts| ts.firstgid <= gid)
+            .filter(|ts| gid - ts.firstgid < ts.tilecount)
+            .map(|ts| (ts, gid - ts.firstgid))
+            .ok_or(TiledError::UnknownGid { gid })
+    }
+
+    fn spawn(&self, obj: &TmjObject) -> Result<(glm::Vec2, glm::Vec2), TiledError> {
+        let facing = match prop(&obj.properties, "facing").and_then(|v| v.as_str()) {
+            Some(name) => compass(name).ok_or_else(|| TiledError::BadFacing(name.into()))?,
+            None => glm::vec2(0., -1.),
+        };
+        Ok((self.center(obj), facing))
+    }
+
+    fn sprite(&self, obj: &TmjObject, gid: u32) -> Result<Sprite, TiledError> {
+        let (ts, local) = self.tileset(gid)?;
+        let mut sprite = Sprite::new(self.center(obj), ts.region(local)?);
+
+        let float = |name| {
+            prop(&obj.properties, name)
+                .and_then(|v| v.as_f64())
+                .map(|v| v as f32)
+        };
+        if let Some(scale) = float("scale") {
+            sprite = sprite.with_scale(glm::vec2(scale, scale));
+        }
+        if let Some(v_offset) = float("v_offset") {
+            sprite = sprite.with_v_offset(v_offset);
+        }
+        if let Some(key) = ts.transparentcolor.as_deref().and_then(parse_color) {
+            sprite = sprite.with_color_key(key);
+        }
+        Ok(sprite)
+    }
+
+    /// Middle of `obj` in tiles. Tile objects are anchored at their bottom
+    /// left corner, every other object at its top left.
+    fn center(&self, obj: &TmjObject) -> glm::Vec2 {
+        let top = if obj.gid.is_some() {
+            obj.y - obj.height
+        } else {
+            obj.y
+        };
+        glm::vec2(
+            (obj.x + obj.width / 2.) / self.tmj.tilewidth as f32,
+            (top + obj.height / 2.) / self.tmj.tileheight as f32,
+        )
+    }
+}
+
+/// Tile and object layers in draw order, with groups expanded.
+fn flatten<'a>(layers: &'a [TmjLayer], out: &mut Vec<&'a TmjLayer>) {
+    for layer in layers {
+        match layer {
+            TmjLayer::Group { layers } => flatten(layers, out),
+            _ => out.push(layer),
+        }
+    }
+}
+
+fn prop<'a>(props: &'a [TmjProperty], name: &str) -> Option<&'a serde_json::Value> {
+    props.iter().find(|p| p.name == name).map(|p| &p.value)
+}
+
+/// Parses Tiled's `#rrggbb` colors.
+fn parse_color(s: &str) -> Option<image::Rgb<u8>> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let v = u32::from_str_radix(hex, 16).ok()?;
+    Some(image::Rgb([(v >> 16) as u8, (v >> 8) as u8, v as u8]))
+}
+
+#[derive(Debug, Deserialize)]
+struct TmjMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<TmjLayer>,
+    #[serde(default)]
+    tilesets: Vec<TmjTileset>,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TmjLayer {
+    TileLayer {
+        name: String,
+        #[serde(default)]
+        width: u32,
+        #[serde(default)]
+        height: u32,
+        data: Option<TmjData>,
+        encoding: Option<String>,
+    },
+    ObjectGroup {
+        objects: Vec<TmjObject>,
+    },
+    Group {
+        layers: Vec<TmjLayer>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TmjData {
+    Gids(Vec<u32>),
+    /// Base64, possibly compressed.
+    Encoded(serde::de::IgnoredAny),
+}
+
+#[derive(Debug, Deserialize)]
+struct TmjTileset {
+    #[serde(default)]
+    firstgid: u32,
+    /// Path of an external tileset, its fields are all missing.
+    source: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    transparentcolor: Option<String>,
+    #[serde(default)]
+    tiles: Vec<TmjTile>,
+}
+
+impl TmjTileset {
+    /// Reads in an external tileset, keeping its first gid.
+    fn resolve(&mut self, dir: Option<&Path>) -> Result<(), TiledError> {
+        let Some(source) = &self.source else {
+            return Ok(());
+        };
+        let Some(dir) = dir else {
+            return Err(TiledError::Unsupported(format!(
+                "external tileset `{}` without a map path, use tiled::load",
+                source
+            )));
+        };
+        let path = dir.join(source);
+        let mut ts: TmjTileset = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        ts.firstgid = self.firstgid;
+        // the image is relative to the tileset, make it relative to the map
+        if let (Some(image), Some(ts_dir)) = (&ts.image, Path::new(source).parent()) {
+            ts.image = Some(ts_dir.join(image).to_string_lossy().into_owned());
+        }
+        *self = ts;
+        Ok(())
+    }
+
+    fn properties(&self, local: u32) -> &[TmjProperty] {
+        self.tiles
+            .iter()
+            .find(|t| t.id == local)
+            .map(|t| t.properties.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn region(&self, local: u32) -> Result<AtlasRegion, TiledError> {
+        if self.image.is_none() || self.columns == 0 {
+            return Err(TiledError::Unsupported(
+                "tilesets made of separate images".into(),
+            ));
+        }
+        let (col, row) = (local % self.columns, local / self.columns);
+        // pixel offset of the `i`th column or row of `size` pixel tiles
+        let offset = |i: u32, size: u32| {
+            size.checked_add(self.spacing)
+                .and_then(|step| step.checked_mul(i))
+                .and_then(|p| p.checked_add(self.margin))
+                .ok_or_else(|| {
+                    TiledError::Unsupported(format!("tile {local} lies past u32 pixels"))
+                })
+        };
+        Ok(AtlasRegion::new(
+            offset(col, self.tilewidth)?,
+            offset(row, self.tileheight)?,
+            self.tilewidth,
+            self.tileheight,
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TmjTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmjProperty {
+    name: String,
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmjObject {
+    #[serde(default)]
+    name: String,
+    /// Called `class` since Tiled 1.9, `type` before.
+    #[serde(default, alias = "class")]
+    r#type: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<TmjProperty>,
+}
+
+impl TmjObject {
+    fn is_spawn(&self) -> bool {
+        self.r#type.eq_ignore_ascii_case("spawn") || self.name.eq_ignore_ascii_case("spawn")
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use nalgebra_glm as glm;
+use raydium::{
+    app::{FixedTimestep, FrameStats},
+    camera::Camera,
+};
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn timestep_carries_leftover_time() {
+    let mut ts = FixedTimestep::new(ms(10));
+    assert_eq!(ts.advance(ms(25)), 2);
+    assert!((ts.alpha() - 0.5).abs() < 1e-5);
+    // leftover 5ms plus 7ms is one more step
+    assert_eq!(ts.advance(ms(7)), 1);
+    assert!((ts.alpha() - 0.2).abs() < 1e-5);
+    assert_eq!(ts.advance(ms(3)), 0);
+}
+
+#[test]
+fn timestep_clamps_long_frames() {
+    let mut ts = FixedTimestep::new(ms(10)).with_max_frame(ms(50));
+    assert_eq!(ts.advance(Duration::from_secs(3)), 5);
+    assert_eq!(ts.alpha(), 0.);
+}
+
+#[test]
+#[should_panic(expected = "fixed timestep of zero")]
+fn timestep_rejects_zero_step() {
+    FixedTimestep::new(Duration::ZERO);
+}
+
+#[test]
+#[should_panic(expected = "fixed timestep of 0 Hz")]
+fn timestep_rejects_zero_hz() {
+    FixedTimestep::from_hz(0);
+}
+
+#[test]
+fn frame_stats_over_window() {
+    let mut stats = FrameStats::new();
+    assert_eq!(stats.fps(), 0.);
+
+    for t in [10, 20, 30] {
+        stats.record(ms(t), 1);
+    }
+    assert_eq!(stats.average(), ms(20));
+    assert_eq!(stats.min(), ms(10));
+    assert_eq!(stats.max(), ms(30));
+    assert!((stats.fps() - 50.).abs() < 1e-3);
+
+    for _ in 0..FrameStats::WINDOW {
+        stats.record(ms(5), 0);
+    }
+    // older frames fell out of the window
+    assert_eq!(stats.max(), ms(5));
+    assert_eq!(stats.frame_count(), 3 + FrameStats::WINDOW as u64);
+    assert_eq!(stats.updates_last_frame(), 0);
+}
+
+#[test]
+fn camera_lerp_blends_pose() {
+    let a = Camera::new(glm::vec2(1., 1.), glm::vec2(1., 0.));
+    let mut b = Camera::new(glm::vec2(3., 1.), glm::vec2(0., 1.));
+    b.set_pitch(0.2);
+
+    let mid = a.lerp(&b, 0.5);
+    assert!(glm::distance(&mid.pos(), &glm::vec2(2., 1.)) < 1e-5);
+    assert!((glm::length(&mid.dir()) - 1.).abs() < 1e-5);
+    assert!(glm::distance(&mid.dir(), &glm::normalize(&glm::vec2(1., 1.))) < 1e-5);
+    assert!(glm::dot(&mid.dir(), &mid.plane()).abs() < 1e-5);
+    assert!((mid.pitch() - 0.1).abs() < 1e-5);
+
+    assert_eq!(a.lerp(&b, 0.), a);
+}
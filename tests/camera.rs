@@ -0,0 +1,160 @@
+use nalgebra_glm as glm;
+use raydium::{
+    camera::{Camera, Camera2D, MAX_RADIUS},
+    map::TileMap,
+};
+
+fn room() -> TileMap {
+    TileMap::from_rows(&[
+        [1, 1, 1, 1, 1],
+        [1, 0, 0, 0, 1],
+        [1, 0, 0, 0, 1],
+        [1, 0, 0, 0, 1],
+        [1, 1, 1, 1, 1],
+    ])
+    .unwrap()
+}
+
+#[test]
+fn fov_sets_plane_length() {
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.));
+    cam.set_fov(90.);
+    assert!((glm::length(&cam.plane()) - 1.).abs() < 1e-5);
+    // plane points to the right of the view
+    assert!(glm::distance(&cam.plane(), &glm::vec2(1., 0.)) < 1e-5);
+}
+
+#[test]
+fn rotate_keeps_plane_perpendicular() {
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.)).with_fov(70.);
+    let plane_len = glm::length(&cam.plane());
+    cam.rotate(1.234);
+    assert!(glm::dot(&cam.dir(), &cam.plane()).abs() < 1e-5);
+    assert!((glm::length(&cam.dir()) - 1.).abs() < 1e-5);
+    assert!((glm::length(&cam.plane()) - plane_len).abs() < 1e-5);
+    assert_eq!(cam.fov(), 70.);
+}
+
+#[test]
+fn walking_into_a_wall_stops_at_radius() {
+    let map = room();
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.)).with_radius(0.25);
+    cam.move_forward(&map, 10.);
+    let pos = cam.pos();
+    assert!((pos.y - 1.25).abs() < 1e-2, "{:?}", pos);
+    assert_eq!(pos.x, 2.5);
+}
+
+#[test]
+fn radius_is_clamped() {
+    let cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.));
+    assert_eq!(cam.clone().with_radius(0.8).radius(), MAX_RADIUS);
+    assert_eq!(cam.with_radius(-1.).radius(), 0.);
+}
+
+#[test]
+fn moving_diagonally_slides_along_walls() {
+    let map = room();
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(1., -1.)).with_radius(0.25);
+    cam.move_forward(&map, 10.);
+    let pos = cam.pos();
+    assert!((pos.x - 3.75).abs() < 1e-2, "{:?}", pos);
+    assert!((pos.y - 1.25).abs() < 1e-2, "{:?}", pos);
+}
+
+#[test]
+fn strafe_moves_right_of_view() {
+    let map = room();
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.));
+    cam.strafe(&map, 0.5);
+    assert!(glm::distance(&cam.pos(), &glm::vec2(3., 2.5)) < 1e-5);
+}
+
+#[test]
+fn zero_direction_keeps_a_valid_view() {
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., 0.));
+    assert_eq!(cam.dir(), glm::vec2(0., -1.));
+    cam.set_dir(glm::vec2(1., 0.));
+    cam.set_dir(glm::vec2(f32::NAN, 0.));
+    assert_eq!(cam.dir(), glm::vec2(1., 0.));
+    assert!(glm::distance(&cam.plane(), &glm::vec2(0., 0.66)) < 1e-5);
+}
+
+#[test]
+fn huge_or_non_finite_moves_are_safe() {
+    let map = room();
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.)).with_radius(0.25);
+    cam.translate(&map, glm::vec2(f32::NAN, 1.));
+    cam.translate(&map, glm::vec2(f32::INFINITY, 0.));
+    assert_eq!(cam.pos(), glm::vec2(2.5, 2.5));
+    // still stops at the wall rather than looping for ages
+    cam.move_forward(&map, 1e30);
+    assert!((cam.pos().y - 1.25).abs() < 1e-2, "{:?}", cam.pos());
+}
+
+#[test]
+fn pitch_moves_horizon_and_is_clamped() {
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.));
+    assert_eq!(cam.horizon(100), 50);
+    cam.look_up(0.1);
+    assert_eq!(cam.horizon(100), 60);
+    cam.look_up(10.);
+    assert_eq!(cam.pitch(), raydium::camera::MAX_PITCH);
+    cam.set_pitch(-0.25);
+    assert_eq!(cam.horizon(100), 25);
+}
+
+fn close(a: glm::Vec2, b: glm::Vec2) -> bool {
+    glm::distance(&a, &b) < 1e-3
+}
+
+#[test]
+fn camera_2d_defaults_to_pixel_coordinates() {
+    let cam = Camera2D::new(glm::vec2(800., 600.));
+    // y grows up in the world and down on screen
+    assert!(close(
+        cam.world_to_screen(glm::vec2(0., 0.)),
+        glm::vec2(0., 600.)
+    ));
+    assert!(close(
+        cam.world_to_screen(glm::vec2(800., 600.)),
+        glm::vec2(800., 0.)
+    ));
+    assert!(close(
+        cam.screen_to_world(glm::vec2(200., 150.)),
+        glm::vec2(200., 450.)
+    ));
+
+    // the camera's position is the middle of clip space
+    let clip = cam.view_proj() * glm::vec4(400., 300., 0., 1.);
+    assert!(close(clip.xy(), glm::vec2(0., 0.)));
+    assert!((0. ..=1.).contains(&clip.z));
+}
+
+#[test]
+fn camera_2d_zoom_and_rotation() {
+    let cam = Camera2D::new(glm::vec2(800., 600.))
+        .with_position(glm::vec2(10., 20.))
+        .with_zoom(4.);
+    // one world unit right of the camera is four pixels right of the middle
+    assert!(close(
+        cam.world_to_screen(glm::vec2(11., 20.)),
+        glm::vec2(404., 300.)
+    ));
+
+    // turning the view left makes the world appear turned right
+    let cam = cam.with_rotation(90.);
+    assert!(close(
+        cam.world_to_screen(glm::vec2(11., 20.)),
+        glm::vec2(400., 304.)
+    ));
+
+    for screen in [
+        glm::vec2(0., 0.),
+        glm::vec2(123., 456.),
+        glm::vec2(800., 1.),
+    ] {
+        let world = cam.screen_to_world(screen);
+        assert!(close(cam.world_to_screen(world), screen));
+    }
+}
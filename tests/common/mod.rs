@@ -0,0 +1,121 @@
+//! Golden image harness shared by the integration tests.
+//!
+//! References live in `tests/golden/<name>.png`. Run the tests with
+//! `RAYDIUM_BLESS=1` to (re)write them from the current output. On a mismatch
+//! the actual frame and a diff image are written to `target/golden/`.
+
+use std::path::PathBuf;
+
+use image::{Rgb, RgbImage};
+
+/// Per-channel difference at or below which two pixels count as equal.
+pub const DEFAULT_TOLERANCE: u8 = 2;
+
+pub struct Golden {
+    name: String,
+    tolerance: u8,
+}
+
+impl Golden {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn reference_path(&self) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", self.name))
+    }
+
+    fn output_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden")
+    }
+
+    /// Compares `actual` against the checked in reference, panicking with the
+    /// number of mismatched pixels if they differ.
+    pub fn check(&self, actual: &RgbImage) {
+        let path = self.reference_path();
+        if std::env::var_os("RAYDIUM_BLESS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            actual.save(&path).unwrap();
+            return;
+        }
+
+        let expected = match image::open(&path) {
+            Ok(img) => img.to_rgb8(),
+            Err(e) => {
+                let out = self.write_output("actual", actual);
+                panic!(
+                    "missing golden image {} ({}), actual frame written to {}. \
+                     Rerun with RAYDIUM_BLESS=1 to accept it.",
+                    path.display(),
+                    e,
+                    out.display()
+                );
+            }
+        };
+
+        if expected.dimensions() != actual.dimensions() {
+            let out = self.write_output("actual", actual);
+            panic!(
+                "golden image {} is {:?} but frame is {:?}, actual frame written to {}",
+                self.name,
+                expected.dimensions(),
+                actual.dimensions(),
+                out.display()
+            );
+        }
+
+        let (diff, mismatched) = diff_images(&expected, actual, self.tolerance);
+        if mismatched > 0 {
+            let actual_out = self.write_output("actual", actual);
+            let diff_out = self.write_output("diff", &diff);
+            panic!(
+                "golden image {} differs in {} pixel(s) (tolerance {}), see {} and {}",
+                self.name,
+                mismatched,
+                self.tolerance,
+                actual_out.display(),
+                diff_out.display()
+            );
+        }
+    }
+
+    fn write_output(&self, suffix: &str, img: &RgbImage) -> PathBuf {
+        let dir = Self::output_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.{}.png", self.name, suffix));
+        img.save(&path).unwrap();
+        path
+    }
+}
+
+/// Returns a diff image, mismatched pixels in red over a dimmed copy of the
+/// expected frame, and the number of mismatched pixels.
+pub fn diff_images(expected: &RgbImage, actual: &RgbImage, tolerance: u8) -> (RgbImage, usize) {
+    let mut mismatched = 0;
+    let diff = RgbImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let e = expected.get_pixel(x, y);
+        let a = actual.get_pixel(x, y);
+        let differs =
+            e.0.iter()
+                .zip(a.0.iter())
+                .any(|(e, a)| e.abs_diff(*a) > tolerance);
+        if differs {
+            mismatched += 1;
+            Rgb([255, 0, 0])
+        } else {
+            let [r, g, b] = e.0;
+            Rgb([r / 4, g / 4, b / 4])
+        }
+    });
+    (diff, mismatched)
+}
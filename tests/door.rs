@@ -0,0 +1,145 @@
+use nalgebra_glm as glm;
+use raydium::{
+    camera::Camera,
+    door::{Door, DoorState, Orientation, ThinWall},
+    map::{Tile, TileMap},
+    ray::{cast_ray, Side},
+};
+
+/// 5x5 room split by a door in the middle row at (2, 2), walls on either
+/// side of it.
+fn room() -> TileMap {
+    let mut map = TileMap::from_rows(&[
+        [1, 1, 1, 1, 1],
+        [1, 0, 0, 0, 1],
+        [1, 1, 0, 1, 1],
+        [1, 0, 0, 0, 1],
+        [1, 1, 1, 1, 1],
+    ])
+    .unwrap();
+    map.set_door(
+        2,
+        2,
+        Tile(2),
+        Door::new(ThinWall::centered(Orientation::Horizontal)).with_speed(2.),
+    );
+    map
+}
+
+#[test]
+fn door_slides_open_and_closed() {
+    let mut door = Door::new(ThinWall::centered(Orientation::Vertical)).with_speed(2.);
+    assert!(door.is_closed());
+
+    door.open();
+    door.update(0.25);
+    assert_eq!(door.state(), DoorState::Opening);
+    assert!((door.amount() - 0.5).abs() < 1e-5);
+
+    // turning around halfway starts from where it is
+    door.toggle();
+    assert_eq!(door.state(), DoorState::Closing);
+    door.update(0.1);
+    assert!((door.amount() - 0.3).abs() < 1e-5);
+
+    door.open();
+    door.update(1.);
+    assert!(door.is_open());
+    assert_eq!(door.amount(), 1.);
+
+    door.close();
+    door.update(1.);
+    assert!(door.is_closed());
+}
+
+#[test]
+fn map_door_api() {
+    let mut map = room();
+    assert!(map.door(2, 2).is_some_and(Door::is_closed));
+    assert!(map.door(1, 1).is_none());
+    assert!(!map.open_door(1, 1));
+    assert!(!map.is_solid(2, 2));
+
+    assert!(map.open_door(2, 2));
+    map.update_doors(1.);
+    assert!(map.door(2, 2).unwrap().is_open());
+    assert_eq!(map.doors().count(), 1);
+    assert_eq!(map.doors().next().unwrap().0, glm::vec2(2, 2));
+
+    // setting a plain tile removes the door
+    map.set(2, 2, Tile::EMPTY);
+    assert!(map.door(2, 2).is_none());
+    assert_eq!(map.doors().count(), 0);
+}
+
+#[test]
+fn rays_hit_the_closed_part_of_doors() {
+    let mut map = room();
+    let origin = glm::vec2(2.25, 3.5);
+    let up = glm::vec2(0., -1.);
+
+    let hit = cast_ray(&map, origin, up, 10.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(2, 2));
+    assert_eq!(hit.side, Side::NorthSouth);
+    assert!((hit.dist - 1.).abs() < 1e-5);
+    assert!((hit.point.y - 2.5).abs() < 1e-5);
+
+    // half open, the ray at x = 2.25 goes through the gap
+    map.open_door(2, 2);
+    map.update_doors(0.25);
+    let hit = cast_ray(&map, origin, up, 10.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(2, 0));
+
+    // the closed half is still hit, with the texture slid along
+    let hit = cast_ray(&map, glm::vec2(2.75, 3.5), up, 10.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(2, 2));
+    assert!((hit.wall_u - 0.75).abs() < 1e-5);
+}
+
+#[test]
+fn rays_hit_thin_walls_in_their_cell() {
+    let mut map = TileMap::new(4, 3);
+    map.set_thin_wall(1, 1, Tile(3), ThinWall::new(Orientation::Vertical, 0.75));
+
+    // starting in the wall's cell, in front of it
+    let hit = cast_ray(&map, glm::vec2(1.25, 1.5), glm::vec2(1., 0.), 10.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(1, 1));
+    assert_eq!(hit.side, Side::EastWest);
+    assert!((hit.dist - 0.5).abs() < 1e-5);
+
+    // from the other side
+    let hit = cast_ray(&map, glm::vec2(3.5, 1.5), glm::vec2(-1., 0.), 10.).unwrap();
+    assert!((hit.point.x - 1.75).abs() < 1e-5);
+
+    // parallel to it
+    assert!(cast_ray(&map, glm::vec2(1.5, 0.5), glm::vec2(0., 1.), 10.).is_none());
+}
+
+#[test]
+fn doors_block_the_camera_until_open() {
+    let mut map = room();
+    let mut cam = Camera::new(glm::vec2(2.5, 3.5), glm::vec2(0., -1.));
+
+    cam.move_forward(&map, 2.);
+    assert!((cam.pos().y - (2.5 + cam.radius())).abs() < 1e-2);
+
+    map.open_door(2, 2);
+    map.update_doors(1.);
+    cam.move_forward(&map, 2.);
+    assert!(cam.pos().y < 2.);
+}
+
+#[test]
+fn thin_walls_block_the_camera() {
+    let mut map = TileMap::new(4, 3);
+    map.set_thin_wall(1, 1, Tile(3), ThinWall::new(Orientation::Vertical, 0.75));
+    let mut cam = Camera::new(glm::vec2(0.5, 1.5), glm::vec2(1., 0.));
+
+    cam.move_forward(&map, 2.);
+    assert!((cam.pos().x - (1.75 - cam.radius())).abs() < 1e-2);
+
+    // walking past its end is fine
+    cam.set_pos(glm::vec2(0.5, 0.5));
+    cam.move_forward(&map, 2.);
+    assert!((cam.pos().x - 2.5).abs() < 1e-5);
+}
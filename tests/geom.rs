@@ -0,0 +1,133 @@
+use nalgebra_glm as glm;
+use raydium::{
+    geom::{Quad, QuadBuffer, Rect},
+    gfx::TextureHandle,
+    math::TransformBuilder,
+};
+
+fn quad(pos: glm::Vec2, z_index: i32) -> Quad {
+    let mut xform = TransformBuilder::new();
+    xform.position(pos).scale(glm::vec2(0.5, 0.25));
+    Quad {
+        xform: xform.build(),
+        color: glm::vec4(0.2, 0.4, 0.6, 1.),
+        z_index,
+        ..Default::default()
+    }
+}
+
+fn approx(a: [f32; 4], b: [f32; 4]) -> bool {
+    a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+}
+
+#[test]
+fn quad_verts_are_transformed_and_colored() {
+    let verts = quad(glm::vec2(1., 2.), 0).calc_into_verts();
+    let corners = [[0.5, 1.75], [1.5, 1.75], [1.5, 2.25], [0.5, 2.25]];
+    for (v, [x, y]) in verts.iter().zip(corners) {
+        assert!(approx(v.pos, [x, y, 0., 1.]), "{:?}", v.pos);
+        assert_eq!(v.color, [0.2, 0.4, 0.6, 1.]);
+    }
+    assert_eq!(verts.map(|v| v.uv), Quad::VERTS.map(|v| v.uv));
+
+    // a quarter turn about the origin takes (1, 0) to (0, 1)
+    let mut xform = TransformBuilder::new();
+    xform.rot(90.);
+    let turned = Quad {
+        xform: xform.build(),
+        ..quad(glm::Vec2::zeros(), 0)
+    };
+    let verts = turned.calc_into_verts();
+    assert!(approx(verts[1].pos, [1., 1., 0., 1.]), "{:?}", verts[1].pos);
+    assert!(
+        approx(verts[2].pos, [-1., 1., 0., 1.]),
+        "{:?}",
+        verts[2].pos
+    );
+}
+
+#[test]
+fn quad_buffer_sorts_by_z_index() {
+    let mut batch = QuadBuffer::empty();
+    batch.push_quad(&quad(glm::vec2(0., 0.), 2));
+    batch.push_quad(&quad(glm::vec2(1., 0.), -1));
+    batch.push_quad(&quad(glm::vec2(2., 0.), 2));
+    batch.push_quad(&quad(glm::vec2(3., 0.), 0));
+    batch.build();
+    assert_eq!(batch.len(), 4);
+
+    // back to front, same z_index in push order
+    let xs: Vec<_> = batch
+        .vertex_buffer()
+        .chunks(4)
+        .map(|v| v[0].pos[0] + 0.5)
+        .collect();
+    assert_eq!(xs, [1., 3., 0., 2.]);
+    assert_eq!(
+        &batch.index_buffer()[..12],
+        &[0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]
+    );
+    assert_eq!(batch.index_buffer().len(), 24);
+
+    batch.clear();
+    batch.build();
+    assert!(batch.is_empty());
+    assert!(batch.vertex_buffer().is_empty());
+}
+
+#[test]
+fn source_rects_are_normalized_against_the_texture() {
+    let sheet = TextureHandle::new(0, 64, 32);
+    let frame = Quad {
+        texture: Some(sheet),
+        src: Some(Rect::new(16., 8., 16., 8.)),
+        ..quad(glm::Vec2::zeros(), 0)
+    };
+    let uvs = frame.calc_into_verts().map(|v| v.uv);
+    // bottom left, bottom right, top right, top left
+    assert_eq!(uvs, [[0.25, 0.5], [0.5, 0.5], [0.5, 0.25], [0.25, 0.25]]);
+
+    // without a source rect the whole texture is drawn
+    let whole = Quad {
+        texture: Some(sheet),
+        ..quad(glm::Vec2::zeros(), 0)
+    };
+    assert_eq!(
+        whole.calc_into_verts().map(|v| v.uv),
+        Quad::VERTS.map(|v| v.uv)
+    );
+}
+
+#[test]
+fn quad_buffer_batches_by_texture() {
+    let (a, b) = (TextureHandle::new(0, 8, 8), TextureHandle::new(1, 8, 8));
+    let textured = |z, texture| Quad {
+        texture,
+        ..quad(glm::Vec2::zeros(), z)
+    };
+
+    let mut batch = QuadBuffer::empty();
+    batch.push_quad(&textured(0, Some(b)));
+    batch.push_quad(&textured(0, Some(a)));
+    batch.push_quad(&textured(0, None));
+    batch.push_quad(&textured(0, Some(b)));
+    batch.push_quad(&textured(1, Some(a)));
+    batch.push_quad(&textured(1, Some(a)));
+    batch.build();
+
+    let draws: Vec<_> = batch
+        .draws()
+        .iter()
+        .map(|d| (d.texture, d.indices.clone()))
+        .collect();
+    assert_eq!(
+        draws,
+        [
+            (None, 0..6),
+            (Some(a), 6..12),
+            (Some(b), 12..24),
+            // a higher z_index never shares a draw with a lower one
+            (Some(a), 24..36),
+        ]
+    );
+}
@@ -0,0 +1,147 @@
+use image::{Rgb, RgbImage};
+use raydium::gfx::{ColumnBuffer, FormatError, PixelBuffer, Scaling, TextureFormat};
+use sdl2::rect::Rect;
+
+const FORMATS: [TextureFormat; 3] = [
+    TextureFormat::Rgb24,
+    TextureFormat::Rgba8888,
+    TextureFormat::Argb8888,
+];
+
+fn pattern(x: u32, y: u32) -> Rgb<u8> {
+    Rgb([x as u8, y as u8, (x * 7 + y * 13) as u8])
+}
+
+#[test]
+fn column_buffer_transposes_into_rows() {
+    // wider than one transpose block and not a multiple of it
+    let (w, h) = (37, 23);
+    let mut columns = ColumnBuffer::new(w, h);
+    for x in 0..w {
+        for y in 0..h {
+            columns.put(x, y, pattern(x, y));
+        }
+    }
+    assert_eq!(columns.column(5)[9], pattern(5, 9));
+
+    let mut rows = RgbImage::new(w, h);
+    columns.transpose_into(&mut rows);
+    assert_eq!(rows, RgbImage::from_fn(w, h, pattern));
+
+    let mut copied = RgbImage::new(w, h);
+    columns.copy_to(&mut copied);
+    assert_eq!(copied, rows);
+}
+
+#[test]
+fn column_bands_cover_the_frame() {
+    let (w, h) = (10, 4);
+    let mut columns = ColumnBuffer::new(w, h);
+    let bands: Vec<_> = columns
+        .bands_mut(4)
+        .map(|(x0, mut band)| {
+            for x in 0..band.width() {
+                for y in 0..h {
+                    band.put(x, y, pattern(x0 + x, y));
+                }
+            }
+            (x0, band.width())
+        })
+        .collect();
+    assert_eq!(bands, [(0, 4), (4, 4), (8, 2)]);
+    assert_eq!(columns.to_rgb_image(), RgbImage::from_fn(w, h, pattern));
+}
+
+#[test]
+fn oversized_bands_are_the_whole_frame() {
+    // u32::MAX * 4 would wrap around in u32
+    let mut columns = ColumnBuffer::new(10, 4);
+    let bands: Vec<_> = columns
+        .bands_mut(u32::MAX)
+        .map(|(x0, band)| (x0, band.width()))
+        .collect();
+    assert_eq!(bands, [(0, 10)]);
+}
+
+#[test]
+fn texture_formats_round_trip() {
+    let (w, h) = (13, 7);
+    let image = RgbImage::from_fn(w, h, pattern);
+    for format in FORMATS {
+        // tightly packed, and with padding at the end of each row
+        for pitch in [format.pitch(w), format.pitch(w) + 5] {
+            let mut bytes = vec![0; pitch * h as usize];
+            format.encode_image(&image, &mut bytes, pitch).unwrap();
+            let decoded = format.decode_image(&bytes, w, h, pitch).unwrap();
+            assert_eq!(decoded, image, "{format:?} with pitch {pitch}");
+        }
+    }
+}
+
+#[test]
+fn texture_format_byte_layout() {
+    let color = Rgb([0x11, 0x22, 0x33]);
+    let encode = |format: TextureFormat| {
+        let mut out = [0; 4];
+        format.encode(color, &mut out);
+        out
+    };
+    assert_eq!(encode(TextureFormat::Rgb24)[..3], [0x11, 0x22, 0x33]);
+    assert_eq!(
+        u32::from_ne_bytes(encode(TextureFormat::Rgba8888)),
+        0x112233ff
+    );
+    assert_eq!(
+        u32::from_ne_bytes(encode(TextureFormat::Argb8888)),
+        0xff112233
+    );
+    assert_eq!(TextureFormat::Rgb24.pitch(10), 30);
+    assert_eq!(TextureFormat::Argb8888.pitch(10), 40);
+}
+
+#[test]
+fn texture_format_checks_buffer_size() {
+    let image = RgbImage::new(4, 3);
+    let format = TextureFormat::Rgba8888;
+
+    let mut short = vec![0; 16 * 3 - 1];
+    assert_eq!(
+        format.encode_image(&image, &mut short, 16),
+        Err(FormatError::BufferSize {
+            expected: 48,
+            actual: 47
+        })
+    );
+    assert!(matches!(
+        format.decode_image(&[0; 48], 4, 3, 12),
+        Err(FormatError::PitchTooSmall { pitch: 12, .. })
+    ));
+    // the last row doesn't need padding
+    assert!(format.decode_image(&[0; 20 * 2 + 16], 4, 3, 20).is_ok());
+}
+
+#[test]
+fn scaling_fits_the_frame_to_the_window() {
+    let window = (1000, 700);
+    assert_eq!(Scaling::Native.resolution(window), window);
+    assert_eq!(Scaling::Native.viewport(window), Rect::new(0, 0, 1000, 700));
+
+    // 3x is the most that fits 320x200 into 1000x700
+    let integer = Scaling::Integer {
+        width: 320,
+        height: 200,
+    };
+    assert_eq!(integer.resolution(window), (320, 200));
+    assert_eq!(integer.viewport(window), Rect::new(20, 50, 960, 600));
+    // too small a window still shows the frame unscaled, cropped
+    assert_eq!(integer.viewport((300, 200)), Rect::new(-10, 0, 320, 200));
+
+    // bars on the top and bottom for a wide window, the sides for a tall one
+    let letterbox = Scaling::Letterbox {
+        width: 320,
+        height: 200,
+    };
+    assert_eq!(letterbox.viewport(window), Rect::new(0, 37, 1000, 625));
+    assert_eq!(letterbox.viewport((800, 800)), Rect::new(0, 150, 800, 500));
+    assert_eq!(letterbox.viewport((1600, 500)), Rect::new(400, 0, 800, 500));
+}
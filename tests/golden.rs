@@ -0,0 +1,415 @@
+mod common;
+
+use common::Golden;
+use nalgebra_glm as glm;
+use raydium::{
+    atlas::{AtlasRegion, TextureAtlas},
+    camera::Camera,
+    door::{Door, Orientation, ThinWall},
+    gfx::{ColumnBuffer, PixelBuffer},
+    gpu_raycast::{GpuRaycaster, FRAME_FORMAT},
+    light::{Fog, Lighting},
+    map::{Tile, TileMap},
+    raycast::{Raycaster, WORLD_MAP},
+    render::{headless_device, NoAdapter},
+    sprite::Sprite,
+};
+
+const W: u32 = 160;
+const H: u32 = 120;
+
+fn world() -> TileMap {
+    TileMap::from_rows(&WORLD_MAP).unwrap()
+}
+
+#[test]
+fn demo_spawn() {
+    let frame = Raycaster::new()
+        .render_frame(
+            &world(),
+            &Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.)),
+            W,
+            H,
+        )
+        .unwrap();
+    Golden::new("demo_spawn").check(&frame);
+}
+
+#[test]
+fn demo_diagonal() {
+    let cam = Camera::new(glm::vec2(4.5, 14.5), glm::vec2(1., -1.));
+    let frame = Raycaster::new().render_frame(&world(), &cam, W, H).unwrap();
+    Golden::new("demo_diagonal").check(&frame);
+}
+
+/// Standing right next to a wall, every column is taller than the screen and
+/// must be clipped at both ends.
+#[test]
+fn wall_taller_than_screen() {
+    let frame = Raycaster::new()
+        .render_frame(
+            &world(),
+            &Camera::new(glm::vec2(1.2, 12.5), glm::vec2(-1., 0.)),
+            W,
+            H,
+        )
+        .unwrap();
+    Golden::new("wall_taller_than_screen").check(&frame);
+}
+
+/// Portrait frame where the wall ends below `width`, the bottom clip must
+/// be done against the height.
+#[test]
+fn portrait_frame() {
+    let frame = Raycaster::new()
+        .render_frame(
+            &world(),
+            &Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.)),
+            H / 2,
+            H * 2,
+        )
+        .unwrap();
+    Golden::new("portrait_frame").check(&frame);
+}
+
+/// Rays that leave a map without a closed border draw nothing.
+#[test]
+fn open_map() {
+    let map = TileMap::from_rows(&[[0, 0, 0, 0, 0], [0, 0, 0, 0, 0], [0, 0, 0, 0, 2]]).unwrap();
+    let frame = Raycaster::new()
+        .render_frame(
+            &map,
+            &Camera::new(glm::vec2(1.5, 1.5), glm::vec2(1., 0.)),
+            W,
+            H,
+        )
+        .unwrap();
+    Golden::new("open_map").tolerance(0).check(&frame);
+}
+
+#[test]
+fn diff_counts_pixels_outside_tolerance() {
+    let a = image::RgbImage::from_pixel(4, 4, image::Rgb([100, 100, 100]));
+    let mut b = a.clone();
+    b.put_pixel(0, 0, image::Rgb([102, 100, 100]));
+    b.put_pixel(1, 0, image::Rgb([100, 110, 100]));
+
+    let (diff, mismatched) = common::diff_images(&a, &b, 2);
+    assert_eq!(mismatched, 1);
+    assert_eq!(diff.get_pixel(1, 0), &image::Rgb([255, 0, 0]));
+
+    let (_, mismatched) = common::diff_images(&a, &b, 0);
+    assert_eq!(mismatched, 2);
+}
+
+/// 3x2 grid of 16x16 textures, each with a different pattern so flipped or
+/// mis-addressed columns show up in the frame. The last column holds sprites
+/// with a magenta color key and a transparent background.
+fn test_atlas() -> TextureAtlas {
+    let img = image::RgbaImage::from_fn(48, 32, |x, y| {
+        let (u, v) = (x % 16, y % 16);
+        let (du, dv) = (u as i32 * 2 - 15, v as i32 * 2 - 15);
+        match (x / 16, y / 16) {
+            (2, 0) if du * du + dv * dv < 14 * 14 => image::Rgba([255, 200, 0, 255]),
+            (2, 0) => image::Rgba([255, 0, 255, 255]),
+            (2, 1) if du.abs() + dv.abs() < 15 => image::Rgba([0, 200, 255, 255]),
+            (2, 1) => image::Rgba([0, 0, 0, 0]),
+            (0, 0) => image::Rgba([u as u8 * 16, 0, 255 - v as u8 * 16, 255]),
+            (1, 0) if (u / 4 + v / 4) % 2 == 0 => image::Rgba([230, 230, 230, 255]),
+            (1, 0) => image::Rgba([40, 40, 40, 255]),
+            (0, 1) if v % 4 == 0 || (u + (v / 4) * 8) % 16 == 0 => image::Rgba([90, 90, 90, 255]),
+            (0, 1) => image::Rgba([170, 80, 40, 255]),
+            _ if u < 4 => image::Rgba([255, 255, 0, 255]),
+            _ => image::Rgba([0, 128, 128, 255]),
+        }
+    });
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+
+    TextureAtlas::from_bytes(png.get_ref())
+        .unwrap()
+        .with_region(Tile(1), AtlasRegion::grid(0, 0, 16, 16))
+        .unwrap()
+        .with_region(Tile(2), AtlasRegion::grid(1, 0, 16, 16))
+        .unwrap()
+        .with_region(Tile(3), AtlasRegion::grid(0, 1, 16, 16))
+        .unwrap()
+        .with_region(Tile(4), AtlasRegion::grid(1, 1, 16, 16))
+        .unwrap()
+}
+
+#[test]
+fn textured_walls() {
+    let cam = Camera::new(glm::vec2(4.5, 14.5), glm::vec2(1., -1.));
+    let frame = Raycaster::with_atlas(test_atlas())
+        .render_frame(&world(), &cam, W, H)
+        .unwrap();
+    Golden::new("textured_walls").check(&frame);
+}
+
+#[test]
+fn textured_wall_taller_than_screen() {
+    let frame = Raycaster::with_atlas(test_atlas())
+        .render_frame(
+            &world(),
+            &Camera::new(glm::vec2(1.2, 12.5), glm::vec2(-1., 0.)),
+            W,
+            H,
+        )
+        .unwrap();
+    Golden::new("textured_wall_taller_than_screen").check(&frame);
+}
+
+#[test]
+fn atlas_rejects_out_of_bounds_regions() {
+    let mut atlas = test_atlas();
+    assert!(atlas
+        .set_region(Tile(5), AtlasRegion::grid(3, 0, 16, 16))
+        .is_err());
+    assert!(atlas
+        .set_region(Tile(5), AtlasRegion::new(0, 0, 0, 16))
+        .is_err());
+    assert!(atlas
+        .set_region(Tile(5), AtlasRegion::new(u32::MAX, 0, 16, 16))
+        .is_err());
+    assert!(atlas
+        .set_region(Tile(5), AtlasRegion::new(0, u32::MAX - 8, 16, 16))
+        .is_err());
+    assert_eq!(atlas.region(Tile(5)), None);
+}
+
+#[test]
+fn floor_and_ceiling() {
+    let mut map = world();
+    map.fill_floor(Tile(3));
+    map.fill_ceiling(Tile(2));
+    // a rug of a different floor texture in front of the spawn
+    for y in 17..21 {
+        for x in 10..15 {
+            map.set_floor(x, y, Tile(1));
+        }
+    }
+
+    let frame = Raycaster::with_atlas(test_atlas())
+        .render_frame(
+            &map,
+            &Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.)),
+            W,
+            H,
+        )
+        .unwrap();
+    Golden::new("floor_and_ceiling").check(&frame);
+}
+
+#[test]
+fn sprites_behind_and_in_front_of_walls() {
+    let coin = AtlasRegion::grid(2, 0, 16, 16);
+    let gem = AtlasRegion::grid(2, 1, 16, 16);
+    let sprites = [
+        // partly hidden by the pillar at (15, 8)
+        Sprite::new(glm::vec2(14.6, 7.5), coin).with_color_key(image::Rgb([255, 0, 255])),
+        Sprite::new(glm::vec2(11.2, 18.5), gem).on_floor(glm::vec2(0.5, 0.5)),
+        Sprite::new(glm::vec2(12.6, 20.), gem).on_floor(glm::vec2(0.3, 0.3)),
+        // behind the camera
+        Sprite::new(glm::vec2(12., 22.8), coin),
+    ];
+
+    let cam = Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.));
+
+    let mut raycaster = Raycaster::with_atlas(test_atlas());
+    let map = world();
+    let mut frame = raycaster.render_frame(&map, &cam, W, H).unwrap();
+    raycaster.render_sprites(&mut frame, &map, &sprites, &cam);
+    Golden::new("sprites").check(&frame);
+}
+
+#[test]
+fn sprites_need_a_frame_of_the_same_width() {
+    let sprites = [Sprite::new(
+        glm::vec2(12., 20.),
+        AtlasRegion::grid(2, 0, 16, 16),
+    )];
+    let cam = Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.));
+    let map = world();
+    let blank = image::RgbImage::new(W, H);
+
+    // nothing rendered yet, then only a narrower frame
+    let mut raycaster = Raycaster::with_atlas(test_atlas());
+    let mut frame = blank.clone();
+    raycaster.render_sprites(&mut frame, &map, &sprites, &cam);
+    assert!(frame == blank);
+    raycaster.render_frame(&map, &cam, W / 2, H).unwrap();
+    raycaster.render_sprites(&mut frame, &map, &sprites, &cam);
+    assert!(frame == blank);
+}
+
+#[test]
+fn looking_up_moves_horizon_down() {
+    let mut map = world();
+    map.fill_floor(Tile(3));
+    map.fill_ceiling(Tile(2));
+
+    let mut cam = Camera::new(glm::vec2(4.5, 14.5), glm::vec2(1., -1.));
+    cam.set_pitch(0.2);
+    let sprites = [
+        Sprite::new(glm::vec2(6.5, 12.5), AtlasRegion::grid(2, 1, 16, 16))
+            .on_floor(glm::vec2(0.5, 0.5)),
+    ];
+
+    let mut raycaster = Raycaster::with_atlas(test_atlas());
+    let mut frame = raycaster.render_frame(&map, &cam, W, H).unwrap();
+    raycaster.render_sprites(&mut frame, &map, &sprites, &cam);
+    Golden::new("pitch_up").check(&frame);
+}
+
+#[test]
+fn half_open_door_and_thin_wall() {
+    let mut map = world();
+    map.set_door(
+        8,
+        8,
+        Tile(2),
+        Door::new(ThinWall::centered(Orientation::Horizontal)),
+    );
+    map.open_door(8, 8);
+    map.update_doors(0.5);
+    map.set_thin_wall(9, 10, Tile(4), ThinWall::new(Orientation::Vertical, 0.25));
+
+    let cam = Camera::new(glm::vec2(8.5, 11.5), glm::vec2(0.2, -1.));
+    let frame = Raycaster::with_atlas(test_atlas())
+        .render_frame(&map, &cam, W, H)
+        .unwrap();
+    Golden::new("door").check(&frame);
+}
+
+#[test]
+fn fog_and_light_levels() {
+    let (map, sprites, cam, mut raycaster) = fog_scene();
+    let mut frame = raycaster.render_frame(&map, &cam, W, H).unwrap();
+    raycaster.render_sprites(&mut frame, &map, &sprites, &cam);
+    Golden::new("fog").check(&frame);
+}
+
+#[test]
+fn threaded_render_matches_serial() {
+    let (map, sprites, cam, mut raycaster) = fog_scene();
+    let mut serial = raycaster.render_frame(&map, &cam, W, H).unwrap();
+    raycaster.render_sprites(&mut serial, &map, &sprites, &cam);
+    // thread counts that split the frame evenly, unevenly and into more
+    // bands than there are cores
+    for threads in [1, 3, 8, 200] {
+        let mut frame = ColumnBuffer::new(W, H);
+        frame.clear_black_horizon(cam.horizon(H));
+        raycaster
+            .render_threaded(&mut frame, &map, &cam, threads)
+            .unwrap();
+        raycaster.render_sprites(&mut frame, &map, &sprites, &cam);
+        assert!(
+            frame.to_rgb_image() == serial,
+            "{threads} threads differ from serial"
+        );
+    }
+}
+
+fn fog_scene() -> (TileMap, [Sprite; 1], Camera, Raycaster) {
+    let mut map = world();
+    map.fill_floor(Tile(3));
+    map.fill_ceiling(Tile(2));
+    // dark strip across the floor, and a dark pillar
+    for x in 0..24 {
+        map.set_light(x, 19, 0.2);
+    }
+    map.set_light(15, 9, 0.);
+    let sprites = [
+        Sprite::new(glm::vec2(11.2, 18.5), AtlasRegion::grid(2, 1, 16, 16))
+            .on_floor(glm::vec2(0.5, 0.5)),
+    ];
+
+    let cam = Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.));
+    let mut raycaster = Raycaster::with_atlas(test_atlas());
+    raycaster.set_lighting(
+        Lighting::default()
+            .with_fog(
+                Fog::Linear {
+                    start: 2.,
+                    end: 12.,
+                },
+                image::Rgb([40, 40, 60]),
+            )
+            .with_ambient(0.1),
+    );
+    (map, sprites, cam, raycaster)
+}
+
+/// Renders `map` with `raycaster` and with a [`GpuRaycaster`] set up the same
+/// way, and checks the frames agree. Float rounding differs between the two
+/// so a few pixels may pick a neighbouring texel row.
+fn assert_gpu_matches(name: &str, map: &TileMap, cam: &Camera, raycaster: &mut Raycaster) {
+    let (device, queue) = match smol::block_on(headless_device(true)) {
+        Ok(gpu) => gpu,
+        Err(e) if e.is::<NoAdapter>() => {
+            eprintln!("skipping, no adapter");
+            return;
+        }
+        Err(e) => panic!("{e:?}"),
+    };
+    let mut gpu = GpuRaycaster::new(&device, &queue, FRAME_FORMAT).unwrap();
+    gpu.upload_map(&device, &queue, map).unwrap();
+    gpu.set_atlas(&device, &queue, raycaster.atlas()).unwrap();
+    gpu.set_lighting(raycaster.lighting().clone());
+
+    let expected = raycaster.render_frame(map, cam, W, H).unwrap();
+    let actual = gpu.render_frame(&device, &queue, cam, W, H).unwrap();
+
+    let (_, mismatched) = common::diff_images(&expected, &actual, common::DEFAULT_TOLERANCE);
+    assert!(
+        mismatched <= (W * H / 100) as usize,
+        "{name}: {mismatched} pixels differ between the software and GPU raycasters"
+    );
+}
+
+#[test]
+fn gpu_matches_software_flat() {
+    let cam = Camera::new(glm::vec2(4.5, 14.5), glm::vec2(1., -1.));
+    assert_gpu_matches("flat", &world(), &cam, &mut Raycaster::new());
+}
+
+#[test]
+fn gpu_matches_software_textured() {
+    let mut map = world();
+    map.fill_floor(Tile(3));
+    map.fill_ceiling(Tile(2));
+    let mut cam = Camera::new(glm::vec2(4.5, 14.5), glm::vec2(1., -1.));
+    cam.set_pitch(0.2);
+    assert_gpu_matches(
+        "textured",
+        &map,
+        &cam,
+        &mut Raycaster::with_atlas(test_atlas()),
+    );
+}
+
+#[test]
+fn gpu_matches_software_door_and_thin_wall() {
+    let mut map = world();
+    map.set_door(
+        8,
+        8,
+        Tile(2),
+        Door::new(ThinWall::centered(Orientation::Horizontal)),
+    );
+    map.open_door(8, 8);
+    map.update_doors(0.5);
+    map.set_thin_wall(9, 10, Tile(4), ThinWall::new(Orientation::Vertical, 0.25));
+
+    let cam = Camera::new(glm::vec2(8.5, 11.5), glm::vec2(0.2, -1.));
+    assert_gpu_matches("door", &map, &cam, &mut Raycaster::with_atlas(test_atlas()));
+}
+
+#[test]
+fn gpu_matches_software_fog() {
+    let (map, _, cam, mut raycaster) = fog_scene();
+    assert_gpu_matches("fog", &map, &cam, &mut raycaster);
+}
@@ -0,0 +1,84 @@
+use nalgebra_glm as glm;
+use raydium::{
+    camera::Camera,
+    input::{Action, InputMap, InputState, MouseLook},
+};
+use sdl2::keyboard::Scancode;
+
+#[test]
+fn default_bindings_drive_axes() {
+    let map = InputMap::default();
+    let mut input = InputState::new();
+
+    input.sample_with(&map, |key| matches!(key, Scancode::W | Scancode::Right));
+    assert_eq!(input.forward(), 1.);
+    assert_eq!(input.turn(), 1.);
+    assert_eq!(input.strafe(), 0.);
+
+    // opposing keys cancel out
+    input.sample_with(&map, |key| matches!(key, Scancode::A | Scancode::D));
+    assert_eq!(input.strafe(), 0.);
+    assert_eq!(input.forward(), 0.);
+}
+
+#[test]
+fn held_state_is_resampled_every_frame() {
+    let map = InputMap::default();
+    let mut input = InputState::new();
+
+    input.sample_with(&map, |key| key == Scancode::Up);
+    assert!(input.is_held(Action::MoveForward));
+    input.sample_with(&map, |_| false);
+    assert!(!input.is_held(Action::MoveForward));
+}
+
+#[test]
+fn keys_can_be_rebound() {
+    let mut map = InputMap::default();
+    map.unbind_action(Action::MoveForward);
+    map.bind(Scancode::I, Action::MoveForward);
+
+    assert_eq!(map.action(Scancode::W), None);
+    assert_eq!(
+        map.keys(Action::MoveForward).collect::<Vec<_>>(),
+        [Scancode::I]
+    );
+
+    let mut input = InputState::new();
+    input.sample_with(&map, |key| key == Scancode::W);
+    assert_eq!(input.forward(), 0.);
+    input.sample_with(&map, |key| key == Scancode::I);
+    assert_eq!(input.forward(), 1.);
+}
+
+#[test]
+fn mouse_look_turns_and_pitches() {
+    let mut cam = Camera::new(glm::vec2(2.5, 2.5), glm::vec2(0., -1.));
+    let mut input = InputState::new();
+    input.sample_mouse_with(100, -50);
+
+    let look = MouseLook {
+        sensitivity: 0.01,
+        pitch_sensitivity: 0.001,
+        vertical: true,
+        ..Default::default()
+    };
+    look.apply(&mut cam, &input);
+    // turned right by one radian, mouse moved up so looking up
+    assert!(glm::distance(&cam.dir(), &glm::vec2(1f32.sin(), -1f32.cos())) < 1e-5);
+    assert!((cam.pitch() - 0.05).abs() < 1e-6);
+
+    let inverted = MouseLook {
+        invert_y: true,
+        ..look.clone()
+    };
+    inverted.apply(&mut cam, &input);
+    assert!(cam.pitch().abs() < 1e-6);
+
+    let flat = MouseLook {
+        vertical: false,
+        ..look
+    };
+    flat.apply(&mut cam, &input);
+    assert!(cam.pitch().abs() < 1e-6);
+}
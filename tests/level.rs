@@ -0,0 +1,153 @@
+use nalgebra_glm as glm;
+use raydium::{
+    atlas::AtlasRegion,
+    level::{Level, LevelError},
+    map::Tile,
+};
+
+const LEVEL: &str = "\
+; test level
+[level]
+name = Test
+facing = east
+atlas = tex.png
+floor = 5
+
+[tiles]
+# = 1
+B = 2 ; pillar
+. = 0
+, = 0 floor 7 ceiling 3
+P = spawn
+
+[textures]
+1 = 0 0 16 16
+7 = 16 0 16 16
+
+[grid]
+#####
+#P.,#
+#.B.#
+#####
+";
+
+#[test]
+fn parses_grid_and_metadata() {
+    let level = Level::parse(LEVEL).unwrap();
+    assert_eq!(level.name.as_deref(), Some("Test"));
+    assert_eq!(level.spawn, glm::vec2(1.5, 1.5));
+    assert_eq!(level.facing, glm::vec2(1., 0.));
+    assert_eq!(
+        level.textures,
+        vec![
+            (Tile(1), AtlasRegion::new(0, 0, 16, 16)),
+            (Tile(7), AtlasRegion::new(16, 0, 16, 16)),
+        ]
+    );
+
+    let map = &level.map;
+    assert_eq!((map.width(), map.height()), (5, 4));
+    assert_eq!(map.get(2, 2), Some(Tile(2)));
+    assert_eq!(map.get(3, 1), Some(Tile::EMPTY));
+    assert_eq!(map.floor(1, 1), Some(Tile(5)));
+    assert_eq!(map.floor(3, 1), Some(Tile(7)));
+    assert_eq!(map.ceiling(3, 1), Some(Tile(3)));
+    assert_eq!(map.ceiling(1, 1), Some(Tile::EMPTY));
+}
+
+#[test]
+fn spawn_can_be_set_by_key() {
+    let src = LEVEL
+        .replace("P = spawn", "P = 0")
+        .replace("floor = 5", "spawn = 3.5 2.25");
+    let level = Level::parse(&src).unwrap();
+    assert_eq!(level.spawn, glm::vec2(3.5, 2.25));
+    assert_eq!(level.camera().pos(), level.spawn);
+}
+
+#[test]
+fn unknown_tile_points_at_symbol() {
+    let src = LEVEL.replace("#.B.#", "#.BX#");
+    match Level::parse(&src) {
+        Err(LevelError::UnknownTile {
+            line,
+            column,
+            symbol,
+        }) => assert_eq!((line, column, symbol), (22, 4, 'X')),
+        other => panic!("expected UnknownTile, got {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_texture_tile_id() {
+    let src = LEVEL.replace("7 = 16", "9 = 16");
+    assert!(matches!(
+        Level::parse(&src),
+        Err(LevelError::UnknownTileId {
+            line: 17,
+            column: 1,
+            id: 9
+        })
+    ));
+}
+
+#[test]
+fn open_border_is_rejected() {
+    let src = LEVEL.replace("#.B.#", "#.B..");
+    assert!(matches!(
+        Level::parse(&src),
+        Err(LevelError::OpenBorder {
+            line: 22,
+            column: 5,
+            x: 4,
+            y: 2
+        })
+    ));
+}
+
+#[test]
+fn missing_and_duplicate_spawn() {
+    let src = LEVEL.replace("#P.,#", "#..,#");
+    assert!(matches!(Level::parse(&src), Err(LevelError::MissingSpawn)));
+
+    let src = LEVEL.replace("#.B.#", "#.BP#");
+    assert!(matches!(
+        Level::parse(&src),
+        Err(LevelError::DuplicateSpawn {
+            line: 22,
+            column: 4
+        })
+    ));
+
+    let src = LEVEL
+        .replace("P = spawn", "P = 0")
+        .replace("floor = 5", "spawn = 2.5 2.5");
+    assert!(matches!(
+        Level::parse(&src),
+        Err(LevelError::SpawnInWall { line: 6, .. })
+    ));
+}
+
+#[test]
+fn syntax_errors_have_positions() {
+    let src = LEVEL.replace("facing = east", "facing = 1 x");
+    match Level::parse(&src) {
+        Err(LevelError::Syntax { line, column, .. }) => assert_eq!((line, column), (4, 12)),
+        other => panic!("expected Syntax, got {:?}", other),
+    }
+
+    let src = LEVEL.replace("#.B.#", "#.B#");
+    assert!(matches!(
+        Level::parse(&src),
+        Err(LevelError::RaggedRow {
+            line: 22,
+            expected: 5,
+            found: 4,
+            ..
+        })
+    ));
+
+    let src = LEVEL.replace("[grid]", "[gird]");
+    let err = Level::parse(&src).unwrap_err();
+    assert_eq!(err.to_string(), "19:1: unknown section [gird]");
+}
@@ -0,0 +1,46 @@
+use image::Rgb;
+use raydium::light::{Fog, Lighting, Shade};
+
+#[test]
+fn default_lighting_changes_nothing() {
+    let lighting = Lighting::default();
+    let c = Rgb([12, 200, 77]);
+    assert_eq!(lighting.shade(1., 100.).apply(c), c);
+    assert_eq!(Shade::NONE.apply(c), c);
+    // unlit cells are black without ambient light
+    assert_eq!(lighting.shade(0., 1.).apply(c), Rgb([0, 0, 0]));
+}
+
+#[test]
+fn linear_fog() {
+    let lighting =
+        Lighting::default().with_fog(Fog::Linear { start: 2., end: 6. }, Rgb([100, 100, 100]));
+    assert_eq!(lighting.fog_amount(1.), 0.);
+    assert!((lighting.fog_amount(4.) - 0.5).abs() < 1e-5);
+    assert_eq!(lighting.fog_amount(10.), 1.);
+
+    let c = Rgb([200, 0, 100]);
+    assert_eq!(lighting.shade(1., 4.).apply(c), Rgb([150, 50, 100]));
+    assert_eq!(lighting.shade(1., 10.).apply(c), Rgb([100, 100, 100]));
+}
+
+#[test]
+fn exponential_fog() {
+    let lighting = Lighting::default().with_fog(Fog::Exponential { density: 0.5 }, Rgb([0, 0, 0]));
+    assert_eq!(lighting.fog_amount(0.), 0.);
+    let a = lighting.fog_amount(2.);
+    assert!((a - (1. - (-1f32).exp())).abs() < 1e-5);
+    assert!(lighting.fog_amount(4.) > a);
+}
+
+#[test]
+fn ambient_raises_dark_cells() {
+    let lighting = Lighting::default().with_ambient(0.25);
+    assert!((lighting.brightness(0.) - 0.25).abs() < 1e-5);
+    assert!((lighting.brightness(0.5) - 0.625).abs() < 1e-5);
+    assert_eq!(lighting.brightness(1.), 1.);
+    assert_eq!(
+        lighting.shade(0., 1.).apply(Rgb([200, 100, 40])),
+        Rgb([50, 25, 10])
+    );
+}
@@ -0,0 +1,16 @@
+use raydium::map::{Tile, TileMap};
+
+#[test]
+fn from_tiles_checks_the_tile_count() {
+    assert!(TileMap::from_tiles(2, 2, vec![Tile(1); 4]).is_ok());
+    assert!(TileMap::from_tiles(2, 2, vec![Tile(1); 3]).is_err());
+    // 65536 * 65536 wraps to 0 in u32
+    assert!(TileMap::from_tiles(1 << 16, 1 << 16, Vec::new()).is_err());
+}
+
+#[test]
+fn from_rows_rejects_ragged_rows() {
+    assert!(TileMap::from_rows(&[vec![1, 1], vec![1]]).is_err());
+    let map = TileMap::from_rows(&[[1, 0, 1], [1, 1, 1]]).unwrap();
+    assert_eq!((map.width(), map.height()), (3, 2));
+}
@@ -0,0 +1,96 @@
+use nalgebra_glm as glm;
+use raydium::{
+    map::{Tile, TileMap},
+    ray::{cast_ray, ray_cells, Side},
+};
+
+/// 6x5 room with a pillar at (3, 2).
+fn room() -> TileMap {
+    TileMap::from_rows(&[
+        [1, 1, 1, 1, 1, 1],
+        [1, 0, 0, 0, 0, 1],
+        [1, 0, 0, 2, 0, 1],
+        [1, 0, 0, 0, 0, 1],
+        [1, 1, 1, 1, 1, 1],
+    ])
+    .unwrap()
+}
+
+#[test]
+fn hits_pillar_face() {
+    let hit = cast_ray(&room(), glm::vec2(1.5, 2.25), glm::vec2(1., 0.), 100.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(3, 2));
+    assert_eq!(hit.tile, Tile(2));
+    assert_eq!(hit.side, Side::EastWest);
+    assert!((hit.perp_dist - 1.5).abs() < 1e-5);
+    assert!((hit.dist - 1.5).abs() < 1e-5);
+    assert!(glm::distance(&hit.point, &glm::vec2(3., 2.25)) < 1e-5);
+    // facing east the right of the view is south, u grows towards the north
+    assert!((hit.wall_u - 0.75).abs() < 1e-5);
+    assert_eq!(hit.normal(glm::vec2(1., 0.)), glm::vec2(-1., 0.));
+    assert!((hit.wall_uv(0.5).y - 0.5).abs() < 1e-5);
+}
+
+#[test]
+fn distances_for_scaled_directions() {
+    let map = room();
+    let origin = glm::vec2(1.5, 3.5);
+    let hit = cast_ray(&map, origin, glm::vec2(0., -2.), 100.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(1, 0));
+    assert_eq!(hit.side, Side::NorthSouth);
+    // perp_dist counts lengths of dir, dist is euclidean
+    assert!((hit.perp_dist - 1.25).abs() < 1e-5);
+    assert!((hit.dist - 2.5).abs() < 1e-5);
+
+    let diag = cast_ray(&map, origin, glm::vec2(1., -1.), 100.).unwrap();
+    assert!((diag.dist - glm::distance(&origin, &diag.point)).abs() < 1e-4);
+}
+
+#[test]
+fn max_dist_limits_hits() {
+    let map = room();
+    let origin = glm::vec2(1.5, 2.5);
+    assert!(cast_ray(&map, origin, glm::vec2(1., 0.), 1.).is_none());
+    assert!(cast_ray(&map, origin, glm::vec2(1., 0.), 1.5).is_some());
+}
+
+#[test]
+fn starting_cell_is_never_hit() {
+    let hit = cast_ray(&room(), glm::vec2(3.5, 2.5), glm::vec2(-1., 0.), 100.).unwrap();
+    assert_eq!(hit.cell, glm::vec2(0, 2));
+}
+
+#[test]
+fn cells_are_visited_in_order() {
+    let map = room();
+    let cells: Vec<_> = ray_cells(&map, glm::vec2(1.5, 1.5), glm::vec2(1., 0.), 100.)
+        .map(|s| (s.cell.x, s.tile.id()))
+        .collect();
+    // walls don't stop the iterator, leaving the map does
+    assert_eq!(cells, vec![(1, 0), (2, 0), (3, 0), (4, 0), (5, 1)]);
+
+    let first = ray_cells(&map, glm::vec2(1.5, 1.5), glm::vec2(1., 1.), 100.)
+        .next()
+        .unwrap();
+    assert_eq!(first.side, None);
+    assert_eq!(first.perp_dist, 0.);
+
+    // a diagonal ray crosses one grid line per step
+    let steps: Vec<_> = ray_cells(&map, glm::vec2(1.25, 1.5), glm::vec2(1., 1.), 2.)
+        .map(|s| s.cell)
+        .collect();
+    for pair in steps.windows(2) {
+        let d = pair[1] - pair[0];
+        assert_eq!(d.x.abs() + d.y.abs(), 1);
+    }
+}
+
+#[test]
+fn zero_direction_only_visits_start() {
+    let map = room();
+    assert_eq!(
+        ray_cells(&map, glm::vec2(2.5, 2.5), glm::vec2(0., 0.), 100.).count(),
+        1
+    );
+    assert!(cast_ray(&map, glm::vec2(2.5, 2.5), glm::vec2(0., 0.), 100.).is_none());
+}
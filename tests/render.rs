@@ -0,0 +1,317 @@
+use image::Rgba;
+use nalgebra_glm as glm;
+use raydium::{
+    app::Present,
+    camera::Camera,
+    geom::{Quad, Rect},
+    gfx::{Scaling, Texture, TextureHandle, TextureType},
+    map::TileMap,
+    math::TransformBuilder,
+    raycast::{Raycaster, WgpuRaycastRenderer, WORLD_MAP},
+    render::{Backdrop, NoAdapter, QuadRenderer, UnknownTexture},
+};
+
+const W: u32 = 64;
+const H: u32 = 48;
+
+/// Offscreen renderer on a software adapter, `None` if the machine has no
+/// adapter at all so the test is skipped.
+fn renderer() -> Option<QuadRenderer> {
+    match smol::block_on(QuadRenderer::offscreen(W, H, true)) {
+        Ok(r) => Some(r),
+        Err(e) if e.is::<NoAdapter>() => {
+            eprintln!("skipping, no adapter");
+            None
+        }
+        Err(e) => panic!("{e:?}"),
+    }
+}
+
+/// Same as [`renderer`] for the raycaster presented through wgpu.
+fn raycast_renderer() -> Option<WgpuRaycastRenderer> {
+    match smol::block_on(WgpuRaycastRenderer::offscreen(W, H, true)) {
+        Ok(r) => Some(r),
+        Err(e) if e.is::<NoAdapter>() => {
+            eprintln!("skipping, no adapter");
+            None
+        }
+        Err(e) => panic!("{e:?}"),
+    }
+}
+
+/// Quad covering `size` pixels centered on `center`, with the renderer's
+/// default camera.
+fn quad(center: glm::Vec2, size: glm::Vec2, color: glm::Vec4, z_index: i32) -> Quad {
+    let mut xform = TransformBuilder::new();
+    xform.position(center).scale(size / 2.);
+    Quad {
+        xform: xform.build(),
+        color,
+        z_index,
+        ..Default::default()
+    }
+}
+
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+#[test]
+fn offscreen_quads_are_drawn_back_to_front() {
+    let Some(mut r) = renderer() else { return };
+
+    r.begin();
+    // pushed first but drawn last
+    r.push_quad(&quad(
+        glm::vec2(32., 24.),
+        glm::vec2(16., 16.),
+        glm::vec4(0., 1., 0., 1.),
+        1,
+    ));
+    r.push_quad(&quad(
+        glm::vec2(32., 24.),
+        glm::vec2(32., 32.),
+        glm::vec4(1., 0., 0., 1.),
+        0,
+    ));
+    r.end();
+    r.present().unwrap();
+
+    let frame = r.read_pixels().unwrap();
+    assert_eq!(frame.dimensions(), (W, H));
+    assert_eq!(*frame.get_pixel(32, 24), GREEN);
+    assert_eq!(*frame.get_pixel(20, 12), RED);
+    assert_eq!(*frame.get_pixel(2, 2), BLACK);
+}
+
+#[test]
+fn offscreen_camera_moves_the_world() {
+    let Some(mut r) = renderer() else { return };
+
+    // world y grows up, so a quad low in the world is low on screen
+    r.begin();
+    r.push_quad(&quad(
+        glm::vec2(8., 8.),
+        glm::vec2(8., 8.),
+        glm::vec4(1., 0., 0., 1.),
+        0,
+    ));
+    r.end();
+    r.present().unwrap();
+    let frame = r.read_pixels().unwrap();
+    assert_eq!(*frame.get_pixel(8, H - 8), RED);
+    assert_eq!(*frame.get_pixel(8, 8), BLACK);
+
+    // zoomed in on the quad it fills the frame
+    let cam = r
+        .camera()
+        .clone()
+        .with_position(glm::vec2(8., 8.))
+        .with_zoom(16.);
+    r.set_camera(cam);
+    r.present().unwrap();
+    let frame = r.read_pixels().unwrap();
+    assert_eq!(*frame.get_pixel(0, 0), RED);
+    assert_eq!(*frame.get_pixel(W - 1, H - 1), RED);
+}
+
+#[test]
+fn resizing_to_nothing_skips_presenting() {
+    let Some(mut r) = renderer() else { return };
+
+    r.begin();
+    r.push_quad(&quad(
+        glm::vec2(8., 8.),
+        glm::vec2(8., 8.),
+        glm::vec4(1., 0., 0., 1.),
+        0,
+    ));
+    r.end();
+    // like a minimized window, keeps the last target
+    r.resize(0, 0);
+    assert_eq!(r.size(), (W, H));
+    r.present().unwrap();
+    assert_ne!(*r.read_pixels().unwrap().get_pixel(8, H - 8), RED);
+
+    r.resize(W, H);
+    r.present().unwrap();
+    assert_eq!(*r.read_pixels().unwrap().get_pixel(8, H - 8), RED);
+}
+
+#[test]
+fn offscreen_textured_quads_sample_their_source_rect() {
+    let Some(mut r) = renderer() else { return };
+
+    // 2x2 sheet, red and green on top, blue and white below
+    let sheet = image::RgbaImage::from_fn(2, 2, |x, y| match (x, y) {
+        (0, 0) => RED,
+        (1, 0) => GREEN,
+        (0, 1) => Rgba([0, 0, 255, 255]),
+        _ => Rgba([255; 4]),
+    });
+    let texture = Texture::from_image(
+        r.device(),
+        r.queue(),
+        &image::DynamicImage::ImageRgba8(sheet),
+        TextureType::Diffuse,
+        Some("sheet"),
+    )
+    .unwrap();
+    let sheet = r.add_texture(texture);
+
+    let sprite = |x, y, center| Quad {
+        texture: Some(sheet),
+        src: Some(Rect::new(x, y, 1., 1.)),
+        ..quad(center, glm::vec2(16., 16.), glm::vec4(1., 1., 1., 1.), 0)
+    };
+    r.begin();
+    r.push_quad(&sprite(1., 0., glm::vec2(16., 24.)));
+    r.push_quad(&sprite(0., 1., glm::vec2(48., 24.)));
+    r.end();
+    r.present().unwrap();
+
+    let frame = r.read_pixels().unwrap();
+    assert_eq!(*frame.get_pixel(16, 24), GREEN);
+    assert_eq!(*frame.get_pixel(48, 24), Rgba([0, 0, 255, 255]));
+
+    // the frame stays in a texture that can be sampled from
+    let target = r.target_texture().unwrap();
+    assert_eq!((target.size.width, target.size.height), (W, H));
+}
+
+#[test]
+fn unknown_texture_handles_fail_to_present() {
+    let Some(mut r) = renderer() else { return };
+    let stranger = TextureHandle::new(7, 8, 8);
+
+    r.begin();
+    r.push_quad(&Quad {
+        texture: Some(stranger),
+        ..quad(
+            glm::vec2(8., 8.),
+            glm::vec2(8., 8.),
+            glm::vec4(1., 1., 1., 1.),
+            0,
+        )
+    });
+    r.end();
+    let err = r.present().unwrap_err();
+    assert!(err.is::<UnknownTexture>(), "{err:?}");
+
+    r.begin();
+    r.end();
+    r.set_backdrop(Some(Backdrop {
+        texture: stranger,
+        viewport: None,
+    }));
+    assert!(r.present().unwrap_err().is::<UnknownTexture>());
+    r.set_backdrop(None);
+    r.present().unwrap();
+}
+
+/// Colors go through an sRGB texture on the way, which may round them off
+/// by one.
+fn assert_close(actual: Rgba<u8>, expected: image::Rgb<u8>, x: u32, y: u32) {
+    let close = actual.0[..3]
+        .iter()
+        .zip(expected.0)
+        .all(|(a, e)| a.abs_diff(e) <= 1);
+    assert!(
+        close,
+        "pixel ({x}, {y}) is {actual:?}, expected {expected:?}"
+    );
+}
+
+#[test]
+fn raycast_frame_is_drawn_under_quads() {
+    let Some(mut r) = raycast_renderer() else {
+        return;
+    };
+    let map = TileMap::from_rows(&WORLD_MAP).unwrap();
+    let cam = Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.));
+
+    r.clear(&cam);
+    r.draw_map(&map, &cam).unwrap();
+    // HUD box in the bottom left corner
+    let quads = r.quads_mut();
+    quads.begin();
+    quads.push_quad(&quad(
+        glm::vec2(8., 8.),
+        glm::vec2(16., 16.),
+        glm::vec4(1., 0., 0., 1.),
+        0,
+    ));
+    quads.end();
+    r.present().unwrap();
+
+    let expected = Raycaster::new().render_frame(&map, &cam, W, H).unwrap();
+    let frame = r.quads().read_pixels().unwrap();
+    for (x, y, &px) in frame.enumerate_pixels() {
+        if x < 16 && y >= H - 16 {
+            assert_eq!(px, RED, "HUD pixel ({x}, {y})");
+        } else {
+            assert_close(px, *expected.get_pixel(x, y), x, y);
+        }
+    }
+}
+
+#[test]
+fn raycast_frame_keeps_its_colors_on_a_linear_target() {
+    let quads = match smol::block_on(QuadRenderer::offscreen_with_format(
+        W,
+        H,
+        wgpu::TextureFormat::Rgba8Unorm,
+        true,
+    )) {
+        Ok(q) => q,
+        Err(e) if e.is::<NoAdapter>() => {
+            eprintln!("skipping, no adapter");
+            return;
+        }
+        Err(e) => panic!("{e:?}"),
+    };
+    let mut r = WgpuRaycastRenderer::with_quads(quads);
+    let map = TileMap::from_rows(&WORLD_MAP).unwrap();
+    let cam = Camera::new(glm::vec2(12., 22.), glm::vec2(0., -1.));
+
+    r.clear(&cam);
+    r.draw_map(&map, &cam).unwrap();
+    r.present().unwrap();
+
+    let expected = Raycaster::new().render_frame(&map, &cam, W, H).unwrap();
+    let frame = r.quads().read_pixels().unwrap();
+    for (x, y, &px) in frame.enumerate_pixels() {
+        assert_close(px, *expected.get_pixel(x, y), x, y);
+    }
+}
+
+#[test]
+fn scaled_raycast_frame_fills_its_viewport() {
+    let Some(mut r) = raycast_renderer() else {
+        return;
+    };
+    let map = TileMap::from_rows(&WORLD_MAP).unwrap();
+    let cam = Camera::new(glm::vec2(4.5, 14.5), glm::vec2(1., -1.));
+
+    // scaled up twice, which leaves bands above and below
+    r.set_scaling(Scaling::Integer {
+        width: W / 2,
+        height: 8,
+    })
+    .unwrap();
+    assert_eq!(r.resolution(), (W / 2, 8));
+    r.clear(&cam);
+    r.draw_map(&map, &cam).unwrap();
+    r.present().unwrap();
+
+    let expected = Raycaster::new().render_frame(&map, &cam, W / 2, 8).unwrap();
+    let frame = r.quads().read_pixels().unwrap();
+    let top = (H - 16) / 2;
+    for (x, y, &px) in frame.enumerate_pixels() {
+        if (top..top + 16).contains(&y) {
+            assert_close(px, *expected.get_pixel(x / 2, (y - top) / 2), x, y);
+        } else {
+            assert_eq!(px, BLACK, "pixel ({x}, {y}) outside of the viewport");
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use nalgebra_glm as glm;
+use raydium::{
+    atlas::AtlasRegion,
+    level::Level,
+    map::Tile,
+    raycast::Raycaster,
+    tiled::{self, TiledError},
+};
+
+/// 5x4 room with a pillar, tileset of 16x16 tiles in a 48x32 image.
+/// Tile 0 (gid 1) is brick, 1 (gid 2) has texture id 4, 2 (gid 3) isn't
+/// solid and 3 (gid 4) is a floor with texture id 5.
+const MAP: &str = r##"{
+  "width": 5, "height": 4, "tilewidth": 16, "tileheight": 16,
+  "infinite": false, "type": "map",
+  "properties": [{ "name": "name", "type": "string", "value": "Room" }],
+  "layers": [
+    { "type": "tilelayer", "name": "Walls", "width": 5, "height": 4,
+      "data": [1, 1, 1, 1, 1,
+               1, 0, 0, 3, 1,
+               1, 0, 2, 0, 1,
+               1, 1, 1, 1, 1] },
+    { "type": "group", "name": "below", "layers": [
+      { "type": "tilelayer", "name": "floor", "width": 5, "height": 4,
+        "data": [0, 0, 0, 0, 0,
+                 0, 4, 4, 4, 0,
+                 0, 4, 4, 4, 0,
+                 0, 0, 0, 0, 0] }
+    ]},
+    { "type": "objectgroup", "name": "things", "objects": [
+      { "id": 1, "name": "", "type": "spawn", "x": 16, "y": 16,
+        "width": 16, "height": 16,
+        "properties": [{ "name": "facing", "type": "string", "value": "east" }] },
+      { "id": 2, "name": "coin", "type": "", "gid": 5, "x": 48, "y": 48,
+        "width": 16, "height": 16,
+        "properties": [{ "name": "scale", "type": "float", "value": 0.5 }] }
+    ]}
+  ],
+  "tilesets": [{
+    "firstgid": 1, "name": "tiles", "image": "tiles.png",
+    "imagewidth": 48, "imageheight": 32, "tilewidth": 16, "tileheight": 16,
+    "columns": 3, "tilecount": 6, "margin": 0, "spacing": 0,
+    "transparentcolor": "#ff00ff",
+    "tiles": [
+      { "id": 1, "properties": [{ "name": "texture", "type": "int", "value": 4 }] },
+      { "id": 2, "properties": [{ "name": "solid", "type": "bool", "value": false }] },
+      { "id": 3, "properties": [{ "name": "texture", "type": "int", "value": 5 }] }
+    ]
+  }]
+}"##;
+
+#[test]
+fn imports_layers_and_properties() {
+    let level = tiled::parse(MAP).unwrap();
+    assert_eq!(level.name.as_deref(), Some("Room"));
+
+    let map = &level.map;
+    assert_eq!((map.width(), map.height()), (5, 4));
+    assert_eq!(map.get(0, 0), Some(Tile(1)));
+    // texture property replaces the gid
+    assert_eq!(map.get(2, 2), Some(Tile(4)));
+    // not solid, left out of the walls
+    assert_eq!(map.get(3, 1), Some(Tile::EMPTY));
+    assert_eq!(map.floor(2, 1), Some(Tile(5)));
+    assert_eq!(map.ceiling(2, 1), Some(Tile::EMPTY));
+
+    assert!(level
+        .textures
+        .contains(&(Tile(1), AtlasRegion::new(0, 0, 16, 16))));
+    assert!(level
+        .textures
+        .contains(&(Tile(4), AtlasRegion::new(16, 0, 16, 16))));
+    assert_eq!(
+        level.atlas.as_deref(),
+        Some(std::path::Path::new("tiles.png"))
+    );
+}
+
+#[test]
+fn imports_spawn_and_sprites() {
+    let level = tiled::parse(MAP).unwrap();
+    assert_eq!(level.spawn, glm::vec2(1.5, 1.5));
+    assert_eq!(level.facing, glm::vec2(1., 0.));
+
+    assert_eq!(level.sprites.len(), 1);
+    let coin = &level.sprites[0];
+    // tile objects hang up from their bottom left corner
+    assert_eq!(coin.pos, glm::vec2(3.5, 2.5));
+    assert_eq!(coin.texture, AtlasRegion::new(16, 16, 16, 16));
+    assert_eq!(coin.scale, glm::vec2(0.5, 0.5));
+    assert_eq!(coin.color_key, Some(image::Rgb([255, 0, 255])));
+}
+
+#[test]
+fn renders_like_the_same_level_file() {
+    let text = Level::parse(
+        "[tiles]\n# = 1\nB = 4\n. = 0\nP = spawn\n[level]\nfacing = east\n[grid]\n#####\n#P..#\n#.B.#\n#####\n",
+    )
+    .unwrap();
+    let tmj = tiled::parse(MAP).unwrap();
+    assert_eq!(text.map.tiles(), tmj.map.tiles());
+
+    let render = |level: &Level| {
+        Raycaster::new()
+            .render_frame(&level.map, &level.camera(), 64, 48)
+            .unwrap()
+    };
+    assert_eq!(render(&text), render(&tmj));
+}
+
+#[test]
+fn rejects_bad_maps() {
+    let no_spawn = MAP.replace(r#""type": "spawn""#, r#""type": "door""#);
+    assert!(matches!(
+        tiled::parse(&no_spawn),
+        Err(TiledError::MissingSpawn)
+    ));
+
+    let no_walls = MAP.replace(r#""name": "Walls""#, r#""name": "rocks""#);
+    assert!(matches!(
+        tiled::parse(&no_walls),
+        Err(TiledError::MissingLayer("walls"))
+    ));
+
+    let unknown = MAP.replace("1, 0, 2, 0, 1", "1, 0, 9, 0, 1");
+    assert!(matches!(
+        tiled::parse(&unknown),
+        Err(TiledError::UnknownGid { gid: 9 })
+    ));
+
+    let base64 = MAP.replace(
+        r#""data": [0, 0, 0, 0, 0,
+                 0, 4, 4, 4, 0,
+                 0, 4, 4, 4, 0,
+                 0, 0, 0, 0, 0]"#,
+        r#""encoding": "base64", "data": "AAAA""#,
+    );
+    assert!(matches!(
+        tiled::parse(&base64),
+        Err(TiledError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn rejects_layers_with_the_wrong_amount_of_data() {
+    let short = MAP.replace(
+        r#""data": [1, 1, 1, 1, 1,
+               1, 0, 0, 3, 1,"#,
+        r#""data": [1, 0, 0, 3, 1,"#,
+    );
+    assert!(matches!(
+        tiled::parse(&short),
+        Err(TiledError::LayerData {
+            len: 15,
+            expected: 20,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_bad_sizes_before_building_the_map() {
+    for (width, height) in [(0, 4), (70000, 70000)] {
+        let sized = MAP.replacen(
+            r#""width": 5, "height": 4,"#,
+            &format!(r#""width": {width}, "height": {height},"#),
+            1,
+        );
+        assert!(
+            matches!(tiled::parse(&sized), Err(TiledError::MapSize { .. })),
+            "{width}x{height}"
+        );
+    }
+
+    let no_tile_width = MAP.replacen(r#""tilewidth": 16"#, r#""tilewidth": 0"#, 1);
+    assert!(matches!(
+        tiled::parse(&no_tile_width),
+        Err(TiledError::TileSize {
+            width: 0,
+            height: 16
+        })
+    ));
+}
+
+#[test]
+fn rejects_conflicting_textures_and_spawns_in_walls() {
+    // gid 1 and gid 2 both claim texture id 4 with different images
+    let conflict = MAP.replace(
+        r#""tiles": ["#,
+        r#""tiles": [
+      { "id": 0, "properties": [{ "name": "texture", "type": "int", "value": 4 }] },"#,
+    );
+    assert!(matches!(
+        tiled::parse(&conflict),
+        Err(TiledError::TextureConflict { id: 4, .. })
+    ));
+
+    let in_wall = MAP.replace(r#""x": 16, "y": 16,"#, r#""x": 0, "y": 0,"#);
+    assert!(matches!(
+        tiled::parse(&in_wall),
+        Err(TiledError::SpawnInWall { .. })
+    ));
+}
+
+#[test]
+fn loads_external_tilesets() {
+    let dir = std::env::temp_dir().join(format!("raydium-tiled-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sets")).unwrap();
+
+    let start = MAP.find(r#""tilesets""#).unwrap();
+    let set_start = MAP[start..].find('{').unwrap() + start;
+    let set_end = MAP.rfind(']').unwrap();
+    let tileset = MAP[set_start..set_end]
+        .trim()
+        .replace(r#""firstgid": 1, "#, "");
+    std::fs::write(dir.join("sets/tiles.tsj"), tileset).unwrap();
+    let map = format!(
+        r#"{}"tilesets": [{{ "firstgid": 1, "source": "sets/tiles.tsj" }}]}}"#,
+        &MAP[..start]
+    );
+    std::fs::write(dir.join("room.tmj"), &map).unwrap();
+
+    assert!(matches!(
+        tiled::parse(&map),
+        Err(TiledError::Unsupported(_))
+    ));
+    let level = tiled::load(dir.join("room.tmj")).unwrap();
+    assert_eq!(level.map.get(2, 2), Some(Tile(4)));
+    assert_eq!(level.atlas, Some(dir.join("sets/tiles.png")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}